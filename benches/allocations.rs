@@ -0,0 +1,29 @@
+//! Counts the heap allocations made by a 5-ply search from the start, to compare builds with
+//! and without the `fast-alloc` feature:
+//! ```text
+//! cargo bench --bench allocations
+//! cargo bench --bench allocations --features fast-alloc
+//! ```
+//! Not timed, since counting every allocation slows them all down.
+
+use chess::{ai::Engine, game::Game};
+
+#[global_allocator]
+static ALLOCATOR: dhat::Alloc = dhat::Alloc;
+
+fn main() {
+    let feature = if cfg!(feature = "fast-alloc") {
+        "with"
+    } else {
+        "without"
+    };
+    let game = Game::new();
+    let mut engine = Engine::new();
+    let _profiler = dhat::Profiler::builder().testing().build();
+    let nodes = engine.search(&game, 5).nodes;
+    let stats = dhat::HeapStats::get();
+    println!(
+        "{nodes} nodes {feature} fast-alloc: {} allocations, {} bytes",
+        stats.total_blocks, stats.total_bytes
+    );
+}
@@ -1,10 +1,224 @@
-use chess::{ai, game::Game};
+use chess::{
+    ai::{self, Engine, SearchOptions},
+    game::{Game, Move, PieceColor, PieceType, Position},
+};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 fn benchmark(c: &mut Criterion) {
     let game = Game::new();
-    c.bench_function("ai", |b| b.iter(|| ai::choose(&game, 4)));
+    c.bench_function("ai", |b| b.iter(|| ai::choose(&game, 4, None)));
 }
 
-criterion_group!(group, benchmark);
+fn deep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep");
+    group.sample_size(10);
+    let game = Game::new();
+    group.bench_function("ai depth 5", |b| b.iter(|| ai::choose(&game, 5, None)));
+    group.finish();
+}
+
+/// the first few Bratko-Kopec test positions, which reward pruning in the middlegame
+const BRATKO_KOPEC: [&str; 4] = [
+    "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - 0 1",
+    "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+    "2q1rr1k/3bbnnp/p2p1pp1/2pPp3/PpP1P1P1/1P2BNNP/2BQ1PRK/7R b - - 0 1",
+    "rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq - 0 1",
+];
+
+fn bratko_kopec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bratko-kopec");
+    group.sample_size(10);
+    for (i, fen) in BRATKO_KOPEC.into_iter().enumerate() {
+        let game = Game::from_fen(fen).expect("valid fen");
+        group.bench_function(format!("bk.{:02} depth 5", i + 1), |b| {
+            b.iter(|| ai::choose(&game, 5, None))
+        });
+    }
+    group.finish();
+}
+
+/// one engine against one per first move on rayon's thread pool, which only pays off with
+/// several cores
+fn parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+    println!("{} threads", rayon::current_num_threads());
+    for (name, game) in [
+        ("start", Game::new()),
+        ("bk.02", Game::from_fen(BRATKO_KOPEC[1]).expect("valid fen")),
+    ] {
+        group.bench_function(format!("{name} depth 5 sequential"), |b| {
+            b.iter(|| ai::choose(&game, 5, None))
+        });
+        group.bench_function(format!("{name} depth 5 parallel"), |b| {
+            b.iter(|| ai::choose_parallel(&game, 5))
+        });
+    }
+    group.finish();
+}
+
+fn killers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("killers");
+    group.sample_size(10);
+    let game = Game::from_fen(BRATKO_KOPEC[1]).expect("valid fen");
+    for killers in [true, false] {
+        let options = SearchOptions {
+            killers,
+            ..SearchOptions::default()
+        };
+        let nodes = Engine::with_options(options).search(&game, 6).nodes;
+        println!("bk.02 depth 6 killers {killers}: {nodes} nodes");
+        group.bench_function(format!("bk.02 depth 6 killers {killers}"), |b| {
+            b.iter(|| Engine::with_options(options).choose(&game, 6))
+        });
+    }
+    group.finish();
+}
+
+/// killers and history together, which both order quiet moves, against neither
+fn quiet_move_ordering(c: &mut Criterion) {
+    let mut group = c.benchmark_group("quiet move ordering");
+    group.sample_size(10);
+    for (i, fen) in BRATKO_KOPEC.into_iter().enumerate() {
+        let game = Game::from_fen(fen).expect("valid fen");
+        for ordered in [true, false] {
+            let options = SearchOptions {
+                killers: ordered,
+                history: ordered,
+                ..SearchOptions::default()
+            };
+            let name = format!("bk.{:02} depth 5 killers and history {ordered}", i + 1);
+            let nodes = Engine::with_options(options).search(&game, 5).nodes;
+            println!("{name}: {nodes} nodes");
+            group.bench_function(name, |b| {
+                b.iter(|| Engine::with_options(options).choose(&game, 5))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn late_move_reductions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("late move reductions");
+    group.sample_size(10);
+    for (name, game) in [
+        ("start", Game::new()),
+        ("bk.02", Game::from_fen(BRATKO_KOPEC[1]).expect("valid fen")),
+    ] {
+        for late_move_reductions in [true, false] {
+            let options = SearchOptions {
+                late_move_reductions,
+                ..SearchOptions::default()
+            };
+            let nodes = Engine::with_options(options).search(&game, 8).nodes;
+            println!("{name} depth 8 late move reductions {late_move_reductions}: {nodes} nodes");
+            group.bench_function(
+                format!("{name} depth 8 late move reductions {late_move_reductions}"),
+                |b| b.iter(|| Engine::with_options(options).choose(&game, 8)),
+            );
+        }
+    }
+    group.finish();
+}
+
+/// positions `depth` plies on from `game`, generating moves with the grouped `moves()`, which
+/// allocates for every piece
+fn walk_grouped(game: &Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    for (from, moves) in game.moves() {
+        for to in moves {
+            let next = if game.is_promotion(from, to) {
+                game.promote(from, to, PieceType::Queen)
+            } else {
+                game.r#move(from, to)
+            };
+            nodes += walk_grouped(&next, depth - 1);
+        }
+    }
+    nodes
+}
+
+/// `walk_grouped`, generating moves with `for_each_move`, which does not allocate
+fn walk_for_each(game: &Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = 0;
+    game.for_each_move(|r#move| match r#move {
+        Move::Promote(.., piece_type) if piece_type != PieceType::Queen => {}
+        _ => nodes += walk_for_each(&game.apply_move_unchecked(r#move), depth - 1),
+    });
+    nodes
+}
+
+fn move_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("move generation");
+    group.sample_size(10);
+    let game = Game::from_fen(BRATKO_KOPEC[3]).expect("valid fen");
+    assert_eq!(walk_grouped(&game, 4), walk_for_each(&game, 4));
+    group.bench_function("moves depth 4", |b| b.iter(|| walk_grouped(&game, 4)));
+    group.bench_function("for_each_move depth 4", |b| {
+        b.iter(|| walk_for_each(&game, 4))
+    });
+    group.finish();
+}
+
+/// positions `depth` plies on from `game` in which the player to move is in check, asking
+/// with `attacks`, which may be `Game::attacks` or `Game::attacks_by_array`
+fn walk_checks(game: &Game, depth: usize, attacks: fn(&Game, PieceColor, Position) -> bool) -> u64 {
+    let check = attacks(game, !game.turn(), game.king_position()) as u64;
+    if depth == 0 {
+        return check;
+    }
+    let mut checks = check;
+    game.for_each_move(|r#move| {
+        checks += walk_checks(&game.apply_move_unchecked(r#move), depth - 1, attacks)
+    });
+    checks
+}
+
+/// check detection with knights and pawns looked up in bitboards against scanning the board
+/// for every piece
+fn check_detection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("check detection");
+    group.sample_size(10);
+    let game = Game::new();
+    assert_eq!(
+        walk_checks(&game, 5, Game::attacks),
+        walk_checks(&game, 5, Game::attacks_by_array)
+    );
+    group.bench_function("bitboards depth 5", |b| {
+        b.iter(|| walk_checks(&game, 5, Game::attacks))
+    });
+    group.bench_function("array depth 5", |b| {
+        b.iter(|| walk_checks(&game, 5, Game::attacks_by_array))
+    });
+    group.finish();
+}
+
+fn perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    let game = Game::new();
+    group.bench_function("perft 4", |b| b.iter(|| game.perft(4)));
+    group.bench_function("perft_parallel 4", |b| b.iter(|| game.perft_parallel(4)));
+    group.finish();
+}
+
+criterion_group!(
+    group,
+    benchmark,
+    deep,
+    bratko_kopec,
+    parallel,
+    killers,
+    quiet_move_ordering,
+    late_move_reductions,
+    move_generation,
+    check_detection,
+    perft
+);
 criterion_main!(group);
@@ -1,40 +1,94 @@
-use std::cmp::Ordering;
+mod evaluator;
+mod pst;
 
-use crate::game::{Game, Outcome, PieceColor, PieceType, Position, PROMOTIONS};
+use std::{
+    cmp::{Ordering, Reverse},
+    ops::Neg,
+    time::{Duration, Instant},
+};
 
-pub enum Move {
-    Move(Position, Position),
-    Promote(Position, Position, PieceType),
-}
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+
+pub use self::evaluator::{
+    CompositeEvaluator, Evaluator, MaterialEvaluator, MobilityEvaluator, NnueEvaluator,
+    PstEvaluator,
+};
+pub use crate::game::Move;
+use crate::{
+    game::{splitmix64, Game, Outcome, PieceColor, PieceType, Position},
+    openingbook::PolyglotBook,
+};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-enum Evaluation {
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Evaluation {
     Outcome(Outcome),
-    Estimate(i8),
+    /// in centipawns, positive favoring white
+    Estimate(i32),
 }
 
 impl Evaluation {
     const MIN: Self = Evaluation::Outcome(Outcome::Win(PieceColor::Black));
     const MAX: Self = Evaluation::Outcome(Outcome::Win(PieceColor::White));
+
+    /// first who has won, if anyone, then the estimate, so that evaluations compare by how good
+    /// they are for white
+    fn key(self) -> (i8, i32) {
+        match self {
+            Evaluation::Outcome(outcome) => match outcome.winner() {
+                Some(PieceColor::White) => (1, 0),
+                Some(PieceColor::Black) => (-1, 0),
+                None => (0, 0),
+            },
+            Evaluation::Estimate(centipawns) => (0, centipawns),
+        }
+    }
+
+    /// the next estimate down, below which no estimate ties with `self`, or `self` if it is
+    /// an outcome
+    fn just_below(self) -> Self {
+        match self {
+            Evaluation::Estimate(centipawns) => Evaluation::Estimate(centipawns - 1),
+            outcome => outcome,
+        }
+    }
+
+    /// `self` from `color`'s point of view rather than white's, as the search sees it: for
+    /// black, a win for black counts as a win for white and each estimate is negated.
+    /// Its own inverse, so it also turns `color`'s point of view back into white's.
+    fn for_color(self, color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => self,
+            PieceColor::Black => -self,
+        }
+    }
+}
+
+/// the same result with the colors swapped
+impl Neg for Evaluation {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Evaluation::Outcome(Outcome::Win(color)) => Evaluation::Outcome(Outcome::Win(!color)),
+            Evaluation::Outcome(Outcome::Resignation(color)) => {
+                Evaluation::Outcome(Outcome::Resignation(!color))
+            }
+            Evaluation::Outcome(Outcome::Timeout(color)) => {
+                Evaluation::Outcome(Outcome::Timeout(!color))
+            }
+            Evaluation::Outcome(Outcome::Draw(reason)) => {
+                Evaluation::Outcome(Outcome::Draw(reason))
+            }
+            Evaluation::Estimate(centipawns) => Evaluation::Estimate(-centipawns),
+        }
+    }
 }
 
+// resigning or running out of time is as decisive as mate, though the search does neither
 impl Ord for Evaluation {
     fn cmp(&self, other: &Self) -> Ordering {
-        use self::Outcome::*;
-        use Evaluation::*;
-        use PieceColor::*;
-        match (self, other) {
-            (Outcome(Win(White)), Outcome(Win(White))) => Ordering::Equal,
-            (Outcome(Win(White)), _) => Ordering::Greater,
-            (_, Outcome(Win(White))) => Ordering::Less,
-            (Outcome(Win(Black)), Outcome(Win(Black))) => Ordering::Equal,
-            (Outcome(Win(Black)), _) => Ordering::Less,
-            (_, Outcome(Win(Black))) => Ordering::Greater,
-            (Outcome(Draw), Outcome(Draw)) => Ordering::Equal,
-            (Outcome(Draw), Estimate(n)) => 0.cmp(n),
-            (Estimate(n), Outcome(Draw)) => n.cmp(&0),
-            (Estimate(n), Estimate(m)) => n.cmp(m),
-        }
+        self.key().cmp(&other.key())
     }
 }
 
@@ -44,7 +98,8 @@ impl PartialOrd for Evaluation {
     }
 }
 
-fn value(piece: PieceType) -> i8 {
+/// in pawns
+pub fn value(piece: PieceType) -> i32 {
     match piece {
         PieceType::Pawn => 1,
         PieceType::Knight => 3,
@@ -55,88 +110,997 @@ fn value(piece: PieceType) -> i8 {
     }
 }
 
-fn estimate(game: Game) -> i8 {
-    game.iter(PieceColor::White)
-        .map(|(_, piece)| value(piece.piece))
-        .sum::<i8>()
-        - game
-            .iter(PieceColor::Black)
-            .map(|(_, piece)| value(piece.piece))
-            .sum::<i8>()
+#[derive(Clone, Copy)]
+enum Bound {
+    Exact,
+    /// the true evaluation is at least this good for the player to move
+    Lower,
+    /// the true evaluation is at most this good for the player to move
+    Upper,
 }
 
-/// REQUIRES: game is not in mate
-/// alpha = the highest value white can force
-/// beta = the lowest value black can force
-fn minimax(
-    game: &Game,
+struct TtEntry {
     depth: usize,
-    mut alpha: Evaluation,
-    mut beta: Evaluation,
-) -> (Move, Evaluation) {
-    let mut best: Option<(Move, Evaluation)> = None;
-    let turn = game.turn();
-    for r#move in game
-        .moves()
-        .flat_map(|(from, to)| to.into_iter().map(move |to| (from, to)))
-        .flat_map(|(from, to)| {
-            if game.is_promotion(from, to) {
-                PROMOTIONS
-                    .into_iter()
-                    .map(|piece_type| Move::Promote(from, to, piece_type))
-                    .collect()
-            } else {
-                vec![Move::Move(from, to)]
+    /// from the point of view of the player to move, as `Engine::negamax` returns it
+    evaluation: Evaluation,
+    bound: Bound,
+    best: Move,
+}
+
+pub struct SearchResult {
+    pub best: Move,
+    /// the principal variation: `best`, then the moves the search expects both sides to play
+    /// after it, at most `depth` in all. It ends early if the game does.
+    pub pv: Vec<Move>,
+    pub evaluation: Evaluation,
+    /// in plies
+    pub depth: usize,
+    /// positions visited over all iterations, a measure of how well the search prunes
+    pub nodes: u64,
+}
+
+/// which optional heuristics the search uses, all on by default.
+/// Turning one off is mostly useful to measure what it is worth.
+#[derive(Clone, Copy)]
+pub struct SearchOptions {
+    /// try quiet moves that refuted a position at the same ply soon after captures
+    pub killers: bool,
+    /// try quiet moves that have caused cutoffs anywhere in the search before other quiet moves
+    pub history: bool,
+    /// search each iteration after the first few in a narrow window around the evaluation of the
+    /// one before, widening it only if the evaluation falls outside
+    pub aspiration: bool,
+    /// search quiet moves late in the move order a ply shallower, and at full depth only if
+    /// that turns out better than what has been found so far
+    pub late_move_reductions: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            killers: true,
+            history: true,
+            aspiration: true,
+            late_move_reductions: true,
+        }
+    }
+}
+
+/// plies from the root beyond which no killer moves are kept
+const MAX_PLY: usize = 64;
+
+/// move ordering scores, see `move_order`
+const CAPTURE_ORDER: i32 = 1 << 30;
+const KILLER_ORDER: i32 = 1 << 29;
+const CHECK_ORDER: i32 = 1 << 28;
+/// between winning, even and losing captures, more than MVV-LVA can tell captures apart by
+const SEE_ORDER: i32 = 1 << 10;
+
+/// deepest iteration searched with a full window, after which each is first searched within
+/// this many centipawns of the evaluation from the iteration before
+const ASPIRATION_DEPTH: usize = 3;
+const ASPIRATION_DELTA: i32 = 50;
+
+/// how many plies shallower than usual to search after passing the turn
+const NULL_MOVE_REDUCTION: usize = 3;
+
+/// how many moves at a node to search at full depth before reducing quiet ones, and how deep
+/// a node must be searched for its moves to be reduced
+const LMR_MOVES: usize = 4;
+const LMR_DEPTH: usize = 3;
+
+/// how many nodes to search between looking at the clock, few enough to stop within a few
+/// milliseconds even in an unoptimized build, where a node can take over 100 microseconds
+const NODES_PER_CLOCK_CHECK: u64 = 64;
+
+/// a list the search builds at every node, such as its moves. With the `fast-alloc` feature
+/// these live on the stack, enough for the most legal moves any position has, 218.
+#[cfg(feature = "fast-alloc")]
+type List<T> = arrayvec::ArrayVec<T, 256>;
+#[cfg(not(feature = "fast-alloc"))]
+type List<T> = Vec<T>;
+
+/// owns search state that outlives a single node, such as the transposition table
+pub struct Engine {
+    table: FxHashMap<u64, TtEntry>,
+    /// when the current search must stop, if it is limited by time
+    deadline: Option<Instant>,
+    /// nodes searched by the current search
+    nodes: u64,
+    options: SearchOptions,
+    /// for each ply from the root, the two quiet moves that most recently caused a cutoff there
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// for each from and to square, how much the quiet move between them has caused cutoffs,
+    /// weighted towards cutoffs far from the leaves
+    history: [[i32; 64]; 64],
+    evaluator: Box<dyn Evaluator>,
+    /// the state of the generator that picks among the best moves at the root when their
+    /// estimates are equal, or None to keep the first
+    random: Option<u64>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::with_options(SearchOptions::default())
+    }
+
+    pub fn with_options(options: SearchOptions) -> Self {
+        Self::with_evaluator(options, Box::new(PstEvaluator::default()))
+    }
+
+    /// an engine that judges positions by `evaluator` rather than the default `PstEvaluator`
+    pub fn with_evaluator(options: SearchOptions, evaluator: Box<dyn Evaluator>) -> Self {
+        let table = FxHashMap::default();
+        let deadline = None;
+        let nodes = 0;
+        let killers = [[None; 2]; MAX_PLY];
+        let history = [[0; 64]; 64];
+        let random = None;
+        Self {
+            table,
+            deadline,
+            nodes,
+            options,
+            killers,
+            history,
+            evaluator,
+            random,
+        }
+    }
+
+    /// picks at random among the best moves when their estimates are equal, rather than the
+    /// first one tried, with the choices determined by `seed`
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Some(seed);
+    }
+
+    /// REQUIRES: game is not in mate
+    pub fn choose(&mut self, game: &Game, depth: usize) -> Move {
+        self.search(game, depth).best
+    }
+
+    /// iterative deepening: searches 1 ply deep, then 2, and so on up to `max_depth`,
+    /// so that each iteration can try the previous iteration's best moves first.
+    /// Always searches at least 1 ply.
+    /// REQUIRES: game is not in mate
+    pub fn search(&mut self, game: &Game, max_depth: usize) -> SearchResult {
+        self.deadline = None;
+        self.nodes = 0;
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history = [[0; 64]; 64];
+        let mut result: Option<SearchResult> = None;
+        for depth in 1..=max_depth.max(1) {
+            self.age_history();
+            let previous = result.as_ref().map(|result| result.evaluation);
+            let (pv, evaluation) = self
+                .search_depth(game, depth, previous)
+                .expect("searches without a deadline run to completion");
+            let pv = self.complete_pv(game, pv, depth);
+            result = Some(SearchResult {
+                best: pv[0],
+                pv,
+                evaluation,
+                depth,
+                nodes: self.nodes,
+            });
+        }
+        result.expect("Engine::search precondition")
+    }
+
+    /// iterative deepening until `budget` runs out, returning the result of the deepest
+    /// iteration to complete. The iteration in progress when time runs out is discarded.
+    /// The first iteration always completes, even if that takes longer than `budget`.
+    /// REQUIRES: game is not in mate
+    pub fn search_for(&mut self, game: &Game, budget: Duration) -> SearchResult {
+        let deadline = Instant::now() + budget;
+        let mut result = self.search(game, 1);
+        self.deadline = Some(deadline);
+        // a deeper iteration started after the deadline could only be thrown away
+        for depth in (2..).take_while(|_| Instant::now() < deadline) {
+            self.age_history();
+            let Some((pv, evaluation)) = self.search_depth(game, depth, Some(result.evaluation))
+            else {
+                break;
+            };
+            let pv = self.complete_pv(game, pv, depth);
+            result = SearchResult {
+                best: pv[0],
+                pv,
+                evaluation,
+                depth,
+                nodes: self.nodes,
+            };
+        }
+        self.deadline = None;
+        result
+    }
+
+    /// one iteration of deepening, given the evaluation from the iteration before if any.
+    /// Mates lie outside every window of estimates, so they always need the full window.
+    fn search_depth(
+        &mut self,
+        game: &Game,
+        depth: usize,
+        previous: Option<Evaluation>,
+    ) -> Option<(Vec<Move>, Evaluation)> {
+        // the search makes and takes back moves on a copy of its own
+        let game = &mut game.clone();
+        let turn = game.turn();
+        let previous = previous.map(|previous| previous.for_color(turn));
+        if let (true, true, Some(Evaluation::Estimate(center))) =
+            (self.options.aspiration, depth > ASPIRATION_DEPTH, previous)
+        {
+            for delta in [ASPIRATION_DELTA, 2 * ASPIRATION_DELTA] {
+                let alpha = Evaluation::Estimate(center - delta);
+                let beta = Evaluation::Estimate(center + delta);
+                let (pv, evaluation) = self.negamax(game, depth, 0, alpha, beta, false)?;
+                // on the edge of the window, the evaluation is only a bound
+                if alpha < evaluation && evaluation < beta {
+                    return Some((pv, evaluation.for_color(turn)));
+                }
+            }
+        }
+        let (pv, evaluation) =
+            self.negamax(game, depth, 0, Evaluation::MIN, Evaluation::MAX, false)?;
+        Some((pv, evaluation.for_color(turn)))
+    }
+
+    /// `pv` carried on with the best moves the transposition table holds for the positions
+    /// after it, up to `depth` moves in all, since a line is cut short wherever the search
+    /// found its position in the table
+    fn complete_pv(&self, game: &Game, mut pv: Vec<Move>, depth: usize) -> Vec<Move> {
+        let mut game = game.clone();
+        for &r#move in &pv {
+            game.make(r#move);
+        }
+        while pv.len() < depth && game.status().is_none() {
+            let Some(entry) = self.table.get(&game.hash()) else {
+                break;
+            };
+            // another position may share the hash
+            if !game.legal_moves().contains(&entry.best) {
+                break;
             }
+            game.make(entry.best);
+            pv.push(entry.best);
+        }
+        pv
+    }
+
+    /// whether the search has run past its deadline, checking the clock only every so often
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        self.deadline.is_some_and(|deadline| {
+            self.nodes.is_multiple_of(NODES_PER_CLOCK_CHECK) && Instant::now() >= deadline
         })
-    {
-        let game = match r#move {
-            Move::Move(from, to) => game.r#move(from, to),
-            Move::Promote(from, to, piece_type) => game.promote(from, to, piece_type),
-        };
-        let evaluation = if let Some(outcome) = game.status() {
-            Evaluation::Outcome(outcome)
-        } else if depth == 0 {
-            Evaluation::Estimate(estimate(game))
-        } else {
-            minimax(&game, depth - 1, alpha, beta).1
-        };
-        match turn {
-            PieceColor::White => {
-                // maximize
-                if best
-                    .as_ref()
-                    .is_none_or(|&(_, best_so_far)| evaluation > best_so_far)
-                {
-                    best = Some((r#move, evaluation))
+    }
+
+    /// remembers `r#move` as having caused a cutoff `ply` plies from the root
+    fn remember_killer(&mut self, r#move: Move, ply: usize) {
+        if !self.options.killers {
+            return;
+        }
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0] != Some(r#move) {
+                killers[1] = killers[0];
+                killers[0] = Some(r#move);
+            }
+        }
+    }
+
+    /// credits `r#move` with causing a cutoff `depth` plies from the leaves
+    fn remember_history(&mut self, r#move: Move, depth: usize) {
+        if !self.options.history {
+            return;
+        }
+        let (from, to) = (r#move.from(), r#move.to());
+        let score = &mut self.history[from.0 as usize][to.0 as usize];
+        *score = score.saturating_add((depth * depth) as i32);
+    }
+
+    /// halves the history scores, so that cutoffs in earlier iterations count for less
+    fn age_history(&mut self) {
+        for score in self.history.as_flattened_mut() {
+            *score >>= 1;
+        }
+    }
+
+    /// REQUIRES: game is not in mate and depth >= 1
+    /// Every evaluation is from the point of view of the player to move, so one player's
+    /// gain is the other's loss: each move's evaluation is the negation of the opponent's
+    /// after it, searched in the window `(-beta, -alpha)`.
+    /// `ply` = how far from the root of the search this position is
+    /// alpha = the highest value the player to move can force
+    /// beta = the highest value the opponent lets the player to move get
+    /// `is_null_move` = whether this position was reached by passing the turn
+    /// Returns the principal variation, at most `depth` moves long, and the evaluation, or
+    /// None if the deadline passed before the search finished.
+    /// Each move is made on `game` and taken back rather than copied, leaving it as it was.
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        depth: usize,
+        ply: usize,
+        mut alpha: Evaluation,
+        beta: Evaluation,
+        is_null_move: bool,
+    ) -> Option<(Vec<Move>, Evaluation)> {
+        if self.out_of_time() {
+            return None;
+        }
+        let key = game.hash();
+        debug_assert_eq!(key, game.zobrist(), "hash out of date");
+        let mut tt_move = None;
+        if let Some(entry) = self.table.get(&key) {
+            tt_move = Some(entry.best);
+            if entry.depth >= depth
+                && match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.evaluation >= beta,
+                    Bound::Upper => entry.evaluation <= alpha,
                 }
+            {
+                return Some((vec![entry.best], entry.evaluation));
+            }
+        }
+        let (original_alpha, original_beta) = (alpha, beta);
+        let mut best: Option<(Vec<Move>, Evaluation)> = None;
+        // searching the root's moves in a window just wider than usual tells a move that ties
+        // with the best so far from a worse one, for breaking ties at random
+        let break_ties = ply == 0 && self.random.is_some();
+        let mut ties = 0;
+        let turn = game.turn();
+        let in_check = game.check();
+        let mut moves = List::new();
+        game.for_each_move(|r#move| moves.push(r#move));
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+        sort_moves(&mut moves, |r#move| {
+            Reverse(move_order(game, r#move, tt_move, &killers, &self.history))
+        });
+        // If passing the turn and searching shallower still does too well for the opponent to
+        // allow, a real move almost certainly would too. Passing is unsound in check, twice in
+        // a row, and in zugzwang, which is likely when only pawns are left to move. Not at the
+        // root, which must come back with a move it has searched.
+        if depth >= NULL_MOVE_REDUCTION
+            && ply > 0
+            && !is_null_move
+            && !in_check
+            && game
+                .iter(turn)
+                .any(|(_, piece)| !matches!(piece.piece, PieceType::Pawn | PieceType::King))
+        {
+            let mut null = game.null_move();
+            if null.status().is_none() {
+                let evaluation = -match depth - NULL_MOVE_REDUCTION {
+                    0 => self.quiesce(&mut null, -beta, -alpha)?,
+                    depth => {
+                        self.negamax(&mut null, depth, ply + 1, -beta, -alpha, true)?
+                            .1
+                    }
+                };
+                // too uncertain to go in the transposition table, and no move was searched to
+                // start a principal variation with
                 if evaluation > beta {
-                    break;
-                }
-                if evaluation > alpha {
-                    alpha = evaluation;
+                    return Some((Vec::new(), evaluation));
                 }
             }
-            PieceColor::Black => {
-                if best
-                    .as_ref()
-                    .is_none_or(|&(_, best_so_far)| evaluation < best_so_far)
-                {
-                    best = Some((r#move, evaluation))
-                }
-                if evaluation < alpha {
-                    break;
-                }
-                if evaluation < beta {
-                    beta = evaluation;
+        }
+        for (index, r#move) in moves.into_iter().enumerate() {
+            let quiet = is_quiet(game, r#move);
+            // every move at the root is worth a full look
+            let reduce = self.options.late_move_reductions
+                && index >= LMR_MOVES
+                && depth >= LMR_DEPTH
+                && ply > 0
+                && quiet
+                && !in_check;
+            let undo = game.make(r#move);
+            // nor are checks, which may be the start of a mating attack
+            let reduce = reduce && !game.check();
+            let floor = if break_ties {
+                alpha.just_below()
+            } else {
+                alpha
+            };
+            let evaluation = self.search_move(game, depth, ply, -beta, -floor, reduce);
+            game.unmake(undo);
+            let (pv, evaluation) = evaluation?;
+            let evaluation = -evaluation;
+            let cutoff = evaluation > beta;
+            if cutoff && quiet {
+                self.remember_killer(r#move, ply);
+                self.remember_history(r#move, depth);
+            }
+            let tied = best
+                .as_ref()
+                .is_some_and(|&(_, best_so_far)| evaluation == best_so_far);
+            if best
+                .as_ref()
+                .is_none_or(|&(_, best_so_far)| evaluation > best_so_far)
+            {
+                ties = 1;
+                best = Some(([vec![r#move], pv].concat(), evaluation));
+            } else if tied && break_ties && matches!(evaluation, Evaluation::Estimate(_)) {
+                // each of the tied moves so far is kept with equal chance
+                ties += 1;
+                let random = self.random.as_mut().expect("breaking ties");
+                if splitmix64(random).is_multiple_of(ties) {
+                    best = Some(([vec![r#move], pv].concat(), evaluation));
                 }
             }
+            if cutoff {
+                break;
+            }
+            alpha = alpha.max(evaluation);
         }
+        let (mut pv, evaluation) = best.expect("negamax precondition");
+        // a line from the transposition table may run deeper than this search
+        pv.truncate(depth);
+        let bound = if evaluation <= original_alpha {
+            Bound::Upper
+        } else if evaluation >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(
+            key,
+            TtEntry {
+                depth,
+                evaluation,
+                bound,
+                best: pv[0],
+            },
+        );
+        Some((pv, evaluation))
+    }
+
+    /// the principal variation from and evaluation of `game`, just reached by a move in a
+    /// position being searched `depth` plies deep, first searching a ply shallower if `reduce`;
+    /// see `negamax`, whose window `alpha` and `beta` are, like the evaluation, from the
+    /// point of view of the player now to move
+    fn search_move(
+        &mut self,
+        game: &mut Game,
+        depth: usize,
+        ply: usize,
+        alpha: Evaluation,
+        beta: Evaluation,
+        reduce: bool,
+    ) -> Option<(Vec<Move>, Evaluation)> {
+        if let Some(outcome) = game.status() {
+            return Some((vec![], Evaluation::Outcome(outcome).for_color(game.turn())));
+        }
+        if depth == 1 {
+            return Some((vec![], self.quiesce(game, alpha, beta)?));
+        }
+        if reduce {
+            let (pv, reduced) = self.negamax(game, depth - 2, ply + 1, alpha, beta, false)?;
+            // only a move that beats the best the player who made it has found so far needs a
+            // closer look
+            if reduced >= beta {
+                return Some((pv, reduced));
+            }
+        }
+        // an unfinished search must not end up in the transposition table
+        self.negamax(game, depth - 1, ply + 1, alpha, beta, false)
+    }
+
+    /// REQUIRES: game is not in mate
+    /// Searches only captures and promotions until the position is quiet, so that the
+    /// evaluator is never trusted in the middle of an exchange. The side to move may also
+    /// "stand pat" on the static evaluation rather than make a losing capture, so captures
+    /// that lose material by static exchange evaluation are not searched at all.
+    /// Like `negamax`, evaluates from the point of view of the player to move, makes and takes
+    /// back each move on `game`, leaving it as it was, and gives up with `None` once out of
+    /// time.
+    fn quiesce(
+        &mut self,
+        game: &mut Game,
+        mut alpha: Evaluation,
+        beta: Evaluation,
+    ) -> Option<Evaluation> {
+        if self.out_of_time() {
+            return None;
+        }
+        let mut best = Evaluation::Estimate(self.evaluator.evaluate(game)).for_color(game.turn());
+        if best > beta {
+            return Some(best);
+        }
+        alpha = alpha.max(best);
+        let mut captures = List::new();
+        game.for_each_move(|r#move| {
+            let (from, to) = (r#move.from(), r#move.to());
+            let tactical = match r#move {
+                Move::Normal(..) => game.is_capture(from, to),
+                // underpromotions rarely change the material balance for the better
+                Move::Promote(.., piece_type) => piece_type == PieceType::Queen,
+            };
+            if tactical && (!game.is_capture(from, to) || game.see(from, to) >= 0) {
+                captures.push(r#move);
+            }
+        });
+        // among equal trades, captures with check first
+        sort_moves(&mut captures, |r#move| {
+            let (from, to) = (r#move.from(), r#move.to());
+            (-mvv_lva(game, from, to), !game.gives_check(from, to))
+        });
+        for r#move in captures {
+            let undo = game.make(r#move);
+            let evaluation = self
+                .quiesce(game, -beta, -alpha)
+                .map(|evaluation| -evaluation);
+            game.unmake(undo);
+            let evaluation = evaluation?;
+            best = best.max(evaluation);
+            if evaluation > beta {
+                break;
+            }
+            alpha = alpha.max(evaluation);
+        }
+        Some(best)
+    }
+}
+
+/// neither a capture nor a promotion
+fn is_quiet(game: &Game, r#move: Move) -> bool {
+    matches!(r#move, Move::Normal(from, to) if !game.is_capture(from, to))
+}
+
+/// sorts `moves` by `key`, computed once for each, keeping moves with equal keys in the order
+/// they were in, as `sort_by_cached_key` does but without allocating under `fast-alloc`
+fn sort_moves<K: Ord>(moves: &mut List<Move>, mut key: impl FnMut(Move) -> K) {
+    let mut keyed: List<(K, usize, Move)> = moves
+        .iter()
+        .enumerate()
+        .map(|(index, &r#move)| (key(r#move), index, r#move))
+        .collect();
+    // no two moves share an index, so an unstable sort keeps ties in order
+    keyed.sort_unstable_by(|(a, a_index, _), (b, b_index, _)| (a, a_index).cmp(&(b, b_index)));
+    for (slot, (_, _, r#move)) in moves.iter_mut().zip(keyed) {
+        *slot = r#move;
+    }
+}
+
+/// how early to try `r#move`: first the best move found by a shallower search, which is likely
+/// to be the best one again, then winning, even and losing captures by static exchange
+/// evaluation, each by MVV-LVA, then killer moves, then checks, then
+/// everything else by history score
+fn move_order(
+    game: &Game,
+    r#move: Move,
+    tt_move: Option<Move>,
+    killers: &[Option<Move>; 2],
+    history: &[[i32; 64]; 64],
+) -> i32 {
+    let (from, to) = (r#move.from(), r#move.to());
+    if Some(r#move) == tt_move {
+        i32::MAX
+    } else if game.is_capture(from, to) {
+        CAPTURE_ORDER + SEE_ORDER * game.see(from, to).signum() + mvv_lva(game, from, to)
+    } else if let Some(slot) = killers.iter().position(|&killer| killer == Some(r#move)) {
+        KILLER_ORDER - slot as i32
+    } else if game.gives_check(from, to) {
+        CHECK_ORDER
+    } else {
+        history[from.0 as usize][to.0 as usize].min(CHECK_ORDER - 1)
     }
-    best.expect("minimax precondition")
 }
 
+/// most valuable victim, least valuable attacker: prefer winning big pieces with small ones
+fn mvv_lva(game: &Game, from: Position, to: Position) -> i32 {
+    let attacker = game.get(from).expect("mvv_lva precondition").piece;
+    // en passant captures a pawn on a vacant square
+    let victim = game.get(to).map_or(PieceType::Pawn, |piece| piece.piece);
+    10 * value(victim) - value(attacker)
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// whether the AI playing `color` agrees to a draw, judging by a search lasting about `budget`.
+/// It accepts when its evaluation is even or worse for it, and declines when it is ahead at
+/// all.
+/// REQUIRES: game is not in mate
+pub fn accepts_draw(game: &Game, color: PieceColor, budget: Duration) -> bool {
+    let evaluation = Engine::new().search_for(game, budget).evaluation;
+    match color {
+        PieceColor::White => evaluation <= Evaluation::Estimate(0),
+        PieceColor::Black => evaluation >= Evaluation::Estimate(0),
+    }
+}
+
+/// the best move found by searching `depth` plies, the principal variation starting with it,
+/// and the evaluation. Equally good moves are picked among by `seed`, if any; see
+/// `Engine::set_seed`.
 /// REQUIRES: game is not in mate
-pub fn choose(game: &Game, depth: usize) -> Move {
-    minimax(game, depth, Evaluation::MIN, Evaluation::MAX).0
+pub fn choose_with_pv(
+    game: &Game,
+    depth: usize,
+    seed: Option<u64>,
+) -> (Move, Vec<Move>, Evaluation) {
+    let mut engine = Engine::new();
+    if let Some(seed) = seed {
+        engine.set_seed(seed);
+    }
+    let SearchResult {
+        best,
+        pv,
+        evaluation,
+        ..
+    } = engine.search(game, depth);
+    (best, pv, evaluation)
+}
+
+/// a move from `book` if it has one for `game`, or else the best move found by searching
+/// `depth` plies
+/// REQUIRES: game is not in mate
+pub fn choose(game: &Game, depth: usize, book: Option<&PolyglotBook>) -> Move {
+    book.and_then(|book| book.probe(game, None))
+        .unwrap_or_else(|| Engine::new().choose(game, depth))
+}
+
+/// the best move found by searching `depth` plies, with the first moves after the best one
+/// split between `Engine`s of their own on rayon's thread pool. The move a search one ply
+/// shallower likes best is searched first, and the rest need only be searched far enough to
+/// tell whether they beat it. Nothing else is shared between the engines, not even the
+/// transposition table, so this searches more nodes in all than `choose` and only makes up
+/// for it with enough cores. It normally finds the same move, but is not guaranteed to:
+/// `choose` also narrows each first move's window by the ones before it, which changes what
+/// gets pruned, and among equally good moves which is picked may differ. Which thread searches
+/// which move, and when, has no effect on the result.
+/// REQUIRES: game is not in mate
+pub fn choose_parallel(game: &Game, depth: usize) -> Move {
+    let depth = depth.max(1);
+    let mut moves = game.legal_moves();
+    if depth > 1 {
+        let first = Engine::new().choose(game, depth - 1);
+        let index = moves
+            .iter()
+            .position(|&r#move| r#move == first)
+            .expect("legal move");
+        moves[..=index].rotate_right(1);
+    }
+    // `r#move`'s evaluation for the player to move if it is better than `alpha`, or else at
+    // most `alpha`
+    let search = |r#move, alpha: Evaluation| {
+        let mut after = game.clone();
+        after.make(r#move);
+        // deepening, as `Engine::search` does, so that each iteration's moves are ordered by
+        // the table the one before filled in
+        let mut engine = Engine::new();
+        let mut evaluation = Evaluation::MIN;
+        for depth in 1..=depth {
+            engine.age_history();
+            (_, evaluation) = engine
+                .search_move(&mut after, depth, 0, Evaluation::MIN, -alpha, false)
+                .expect("searches without a deadline run to completion");
+        }
+        -evaluation
+    };
+    let mut best = (moves[0], search(moves[0], Evaluation::MIN));
+    let evaluations: Vec<(Move, Evaluation)> = moves[1..]
+        .par_iter()
+        .map(|&r#move| (r#move, search(r#move, best.1)))
+        .collect();
+    // the first of equally good moves
+    for (r#move, evaluation) in evaluations {
+        if evaluation > best.1 {
+            best = (r#move, evaluation);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{
+        choose, choose_with_pv, Engine, Evaluation, MobilityEvaluator, Move, PstEvaluator,
+        SearchOptions,
+    };
+    use crate::game::{square, Game, Outcome, PieceColor};
+
+    /// the default evaluator as it was before it counted mobility, for tests that pin down
+    /// what the search found with it
+    fn without_mobility() -> PstEvaluator {
+        PstEvaluator {
+            mobility: MobilityEvaluator { weight: 0 },
+            ..PstEvaluator::default()
+        }
+    }
+
+    #[test]
+    fn quiescence_sees_recapture() {
+        // Qxd5 wins a pawn at depth 1, but exd5 then wins the queen
+        let game = Game::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").expect("valid fen");
+        assert_ne!(
+            choose(&game, 1, None),
+            Move::Normal("d1".parse().unwrap(), "d5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn sensible_opening() {
+        let sensible = [("e2", "e4"), ("d2", "d4"), ("g1", "f3"), ("b1", "c3")]
+            .map(|(from, to)| Move::Normal(square(from), square(to)));
+        let opening = choose(&Game::new(), 3, None);
+        assert!(sensible.contains(&opening), "{opening:?}");
+    }
+
+    #[test]
+    fn principal_variation() {
+        let (best, pv, _) = choose_with_pv(&Game::new(), 4, None);
+        assert_eq!(pv.len(), 4, "{pv:?}");
+        assert_eq!(pv[0], best);
+        let mut game = Game::new();
+        for r#move in pv {
+            game = game.apply(r#move).expect("legal");
+        }
+    }
+
+    #[test]
+    fn negamax() {
+        // what the search found before it was written as negamax, with code of its own for
+        // each color, and before the evaluation counted mobility
+        for (fen, best, evaluation) in [
+            (
+                "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - 0 1",
+                "d6d1",
+                Evaluation::Outcome(Outcome::Win(PieceColor::Black)),
+            ),
+            (
+                "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+                "e4e5",
+                Evaluation::Estimate(65),
+            ),
+            (
+                "2q1rr1k/3bbnnp/p2p1pp1/2pPp3/PpP1P1P1/1P2BNNP/2BQ1PRK/7R b - - 0 1",
+                "f6f5",
+                Evaluation::Estimate(60),
+            ),
+            (
+                "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+                "d1d8",
+                Evaluation::Outcome(Outcome::Win(PieceColor::White)),
+            ),
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let result =
+                Engine::with_evaluator(SearchOptions::default(), Box::new(without_mobility()))
+                    .search(&game, 5);
+            assert_eq!(result.best.to_string(), best, "{fen}");
+            assert_eq!(result.evaluation, evaluation, "{fen}");
+        }
+        let evaluation = Evaluation::Outcome(Outcome::Resignation(PieceColor::White));
+        assert_eq!(-evaluation, evaluation.for_color(PieceColor::Black));
+        assert_eq!(-(-evaluation), evaluation);
+        assert!(-Evaluation::Estimate(30) < Evaluation::Estimate(-20));
+    }
+
+    #[test]
+    fn parallel() {
+        // positions with one move better than the rest, unlike the start, where either search
+        // may pick any of four
+        for fen in [
+            "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+            // mate in one, and a queen left hanging
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+            "rnb1kbnr/pppp1ppp/8/4p1q1/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1",
+            "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            assert_eq!(
+                super::choose_parallel(&game, 4),
+                choose(&game, 4, None),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn parallel_ignores_scheduling() {
+        // from the start, where several moves are equally good
+        let game = Game::new();
+        let chosen: Vec<Move> = [1, 2, 8, 8]
+            .into_iter()
+            .map(|threads| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .expect("thread pool")
+                    .install(|| super::choose_parallel(&game, 3))
+            })
+            .collect();
+        assert!(
+            chosen.windows(2).all(|pair| pair[0] == pair[1]),
+            "{chosen:?}"
+        );
+    }
+
+    #[test]
+    fn hash_kept_up_to_date() {
+        // `negamax` checks the hash against one computed from scratch at every node
+        let result = Engine::new().search(&Game::new(), 4);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn random_ties() {
+        // many pawn moves are as good as each other
+        let game =
+            Game::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").expect("valid fen");
+        let unseeded = Engine::new().search(&game, 2);
+        let seeded = |seed| {
+            let mut engine = Engine::new();
+            engine.set_seed(seed);
+            engine.search(&game, 2)
+        };
+        let mut chosen = Vec::new();
+        for seed in 0..8 {
+            let result = seeded(seed);
+            assert_eq!(result.evaluation, unseeded.evaluation);
+            assert_eq!(seeded(seed).best, result.best);
+            chosen.push(result.best);
+        }
+        assert!(chosen.iter().any(|&best| best != chosen[0]), "{chosen:?}");
+    }
+
+    #[test]
+    fn accepts_draw() {
+        let game = Game::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").expect("valid fen");
+        let budget = Duration::from_millis(100);
+        assert!(!super::accepts_draw(&game, PieceColor::White, budget));
+        assert!(super::accepts_draw(&game, PieceColor::Black, budget));
+        let game = Game::from_fen("8/8/8/4k3/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        assert!(super::accepts_draw(&game, PieceColor::White, budget));
+        assert!(super::accepts_draw(&game, PieceColor::Black, budget));
+        // a knight up at any depth the search reaches, so the decision does not hang on timing
+        let game = Game::from_fen("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .expect("valid fen");
+        assert!(!super::accepts_draw(&game, PieceColor::White, budget));
+        assert!(super::accepts_draw(&game, PieceColor::Black, budget));
+        let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1")
+            .expect("valid fen");
+        assert!(super::accepts_draw(&game, PieceColor::White, budget));
+        assert!(!super::accepts_draw(&game, PieceColor::Black, budget));
+    }
+
+    #[test]
+    fn killers() {
+        // killers cannot help where there is nothing to order, but overall they should
+        let mut nodes = (0, 0);
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+            "rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let search = |killers| {
+                let options = SearchOptions {
+                    killers,
+                    ..SearchOptions::default()
+                };
+                Engine::with_evaluator(options, Box::new(without_mobility())).search(&game, 4)
+            };
+            let (with, without) = (search(true), search(false));
+            assert_eq!(with.best, without.best, "{fen}");
+            assert_eq!(with.evaluation, without.evaluation, "{fen}");
+            assert!(with.nodes <= without.nodes, "{fen}");
+            nodes = (nodes.0 + with.nodes, nodes.1 + without.nodes);
+        }
+        assert!(nodes.0 < nodes.1, "{nodes:?}");
+    }
+
+    #[test]
+    fn history() {
+        // killers are off so that the history table orders every quiet move, and late move
+        // reductions so that ordering them better saves the whole subtree
+        let mut nodes = (0, 0);
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let search = |history| {
+                Engine::with_options(SearchOptions {
+                    killers: false,
+                    history,
+                    late_move_reductions: false,
+                    ..SearchOptions::default()
+                })
+                .search(&game, 5)
+            };
+            let (with, without) = (search(true), search(false));
+            assert_eq!(with.evaluation, without.evaluation, "{fen}");
+            nodes = (nodes.0 + with.nodes, nodes.1 + without.nodes);
+        }
+        // at least a quarter fewer
+        assert!(4 * nodes.0 < 3 * nodes.1, "{nodes:?}");
+    }
+
+    #[test]
+    fn aspiration() {
+        // a narrow window changes how much is pruned, but not what is found
+        let mut nodes = (0, 0);
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+            "rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let search = |aspiration| {
+                Engine::with_options(SearchOptions {
+                    aspiration,
+                    ..SearchOptions::default()
+                })
+                .search(&game, 5)
+            };
+            let (with, without) = (search(true), search(false));
+            assert_eq!(with.best, without.best, "{fen}");
+            nodes = (nodes.0 + with.nodes, nodes.1 + without.nodes);
+        }
+        assert!(nodes.0 < nodes.1, "{nodes:?}");
+    }
+
+    #[test]
+    fn late_move_reductions() {
+        let mut nodes = (0, 0);
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            "3r1k2/4npp1/1ppr3p/p6P/P2PPPP1/1NR5/5K2/2R5 w - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let search = |late_move_reductions| {
+                Engine::with_options(SearchOptions {
+                    late_move_reductions,
+                    ..SearchOptions::default()
+                })
+                .search(&game, 5)
+            };
+            let (with, without) = (search(true), search(false));
+            nodes = (nodes.0 + with.nodes, nodes.1 + without.nodes);
+        }
+        assert!(nodes.0 < nodes.1, "{nodes:?}");
+    }
+
+    #[test]
+    fn search_for_stops_in_time() {
+        let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+            .expect("valid fen");
+        for budget in [1, 200].map(Duration::from_millis) {
+            let start = Instant::now();
+            let result = Engine::new().search_for(&game, budget);
+            // slack only for the first iteration and the nodes between looks at the clock
+            assert!(
+                start.elapsed() < budget + Duration::from_millis(50),
+                "{budget:?}: {:?}",
+                start.elapsed()
+            );
+            assert!(result.depth >= 1);
+        }
+    }
+
+    #[test]
+    fn mates_with_king_and_queen() {
+        let mut game = Game::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").expect("valid fen");
+        let mut engine = Engine::new();
+        for _ in 0..20 {
+            if game.status().is_some() {
+                break;
+            }
+            game = game.apply_move_unchecked(engine.choose(&game, 3));
+        }
+        assert_eq!(
+            game.status(),
+            Some(Outcome::Win(PieceColor::White)),
+            "{game:?}"
+        );
+    }
 }
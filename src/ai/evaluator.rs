@@ -0,0 +1,251 @@
+use crate::{
+    ai::{pst, value},
+    game::{Game, PieceColor, PieceType},
+};
+
+/// a static evaluation of positions, for the search to fall back on where it stops looking ahead
+pub trait Evaluator: Send + Sync {
+    /// in centipawns, positive favoring white
+    fn evaluate(&self, game: &Game) -> i32;
+}
+
+/// how much a piece counts towards the game being in its middle rather than its end
+fn phase_weight(piece: PieceType) -> i32 {
+    match piece {
+        PieceType::Pawn | PieceType::King => 0,
+        PieceType::Knight | PieceType::Bishop => 1,
+        PieceType::Rook => 2,
+        PieceType::Queen => 4,
+    }
+}
+
+/// total phase weight of the starting position
+const MIDGAME_PHASE: i32 = 24;
+
+/// centipawns per pawn in `color`'s longest pawn chain beyond the first
+const PAWN_CHAIN_BONUS: i32 = 5;
+
+/// bonus for how well `color`'s pawns protect each other
+fn pawn_structure(game: &Game, color: PieceColor) -> i32 {
+    PAWN_CHAIN_BONUS * game.longest_pawn_chain(color).saturating_sub(1) as i32
+}
+
+/// centipawns per square a piece can move to
+const MOBILITY_WEIGHT: i32 = 4;
+
+/// centipawns per step the lone king is from the centre or the kings are from each other
+const MOP_UP_WEIGHT: i32 = 20;
+
+/// When one side has nothing but its king and the other has more than pawns, material alone
+/// gives the search no way to make progress towards mate. Instead reward the stronger side
+/// for driving the lone king to the edge and bringing its own king close, positive favoring white.
+fn mop_up(game: &Game) -> i32 {
+    let king = |color| {
+        game.iter(color)
+            .find(|(_, piece)| piece.piece == PieceType::King)
+            .map(|(position, _)| position)
+            .expect("king always exists")
+    };
+    let bare = |color| {
+        game.iter(color)
+            .all(|(_, piece)| piece.piece == PieceType::King)
+    };
+    let has_pieces = |color| {
+        game.iter(color)
+            .any(|(_, piece)| !matches!(piece.piece, PieceType::King | PieceType::Pawn))
+    };
+    for (strong, sign) in [(PieceColor::White, 1), (PieceColor::Black, -1)] {
+        if bare(!strong) && has_pieces(strong) {
+            let (strong_king, weak_king) = (king(strong), king(!strong));
+            let kings_distance = strong_king.rank().abs_diff(weak_king.rank())
+                + strong_king.file().abs_diff(weak_king.file());
+            return sign
+                * MOP_UP_WEIGHT
+                * (weak_king.center_distance() as i32 + 14 - kings_distance as i32);
+        }
+    }
+    0
+}
+
+fn sign(color: PieceColor) -> i32 {
+    match color {
+        PieceColor::White => 1,
+        PieceColor::Black => -1,
+    }
+}
+
+/// material, plus pawn structure and mop-up, without regard to where the pieces stand
+#[derive(Clone, Copy, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, game: &Game) -> i32 {
+        let mut evaluation = mop_up(game);
+        for color in [PieceColor::White, PieceColor::Black] {
+            let material: i32 = game
+                .iter(color)
+                .map(|(_, piece)| value(piece.piece) * 100)
+                .sum();
+            evaluation += sign(color) * (material + pawn_structure(game, color));
+        }
+        evaluation
+    }
+}
+
+/// a bonus for every square each knight, bishop, rook and queen can move to, so that pieces
+/// get developed rather than left stuck behind their own
+#[derive(Clone, Copy)]
+pub struct MobilityEvaluator {
+    /// centipawns per square
+    pub weight: i32,
+}
+
+impl Default for MobilityEvaluator {
+    fn default() -> Self {
+        Self {
+            weight: MOBILITY_WEIGHT,
+        }
+    }
+}
+
+impl Evaluator for MobilityEvaluator {
+    fn evaluate(&self, game: &Game) -> i32 {
+        let mobility = |color| game.mobility(color) as i32;
+        self.weight * (mobility(PieceColor::White) - mobility(PieceColor::Black))
+    }
+}
+
+/// `MaterialEvaluator` and `MobilityEvaluator` plus piece-square bonuses, tapered between
+/// midgame and endgame tables according to how much non-pawn material remains. The AI's default.
+#[derive(Clone, Copy, Default)]
+pub struct PstEvaluator {
+    pub material: MaterialEvaluator,
+    pub mobility: MobilityEvaluator,
+}
+
+impl Evaluator for PstEvaluator {
+    fn evaluate(&self, game: &Game) -> i32 {
+        let mut phase = 0;
+        let mut midgame = 0;
+        let mut endgame = 0;
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (position, piece) in game.iter(color) {
+                let (midgame_bonus, endgame_bonus) = pst::bonus(piece, position);
+                midgame += sign(color) * midgame_bonus;
+                endgame += sign(color) * endgame_bonus;
+                phase += phase_weight(piece.piece);
+            }
+        }
+        let phase = phase.min(MIDGAME_PHASE);
+        self.material.evaluate(game)
+            + self.mobility.evaluate(game)
+            + (midgame * phase + endgame * (MIDGAME_PHASE - phase)) / MIDGAME_PHASE
+    }
+}
+
+/// a weighted sum of other evaluators
+pub struct CompositeEvaluator {
+    components: Vec<Box<dyn Evaluator>>,
+    weights: Vec<i32>,
+}
+
+impl CompositeEvaluator {
+    /// REQUIRES: there are as many weights as components
+    pub fn new(components: Vec<Box<dyn Evaluator>>, weights: Vec<i32>) -> Self {
+        assert_eq!(
+            components.len(),
+            weights.len(),
+            "CompositeEvaluator::new precondition"
+        );
+        Self {
+            components,
+            weights,
+        }
+    }
+}
+
+impl Evaluator for CompositeEvaluator {
+    fn evaluate(&self, game: &Game) -> i32 {
+        self.components
+            .iter()
+            .zip(&self.weights)
+            .map(|(component, weight)| weight * component.evaluate(game))
+            .sum()
+    }
+}
+
+/// where an efficiently updatable neural network will evaluate positions
+#[derive(Clone, Copy, Default)]
+pub struct NnueEvaluator;
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&self, _game: &Game) -> i32 {
+        todo!("NNUE evaluation")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CompositeEvaluator, Evaluator, MaterialEvaluator, MobilityEvaluator, PstEvaluator,
+        MOBILITY_WEIGHT,
+    };
+    use crate::game::Game;
+
+    #[test]
+    fn king_prefers_shelter_then_centre() {
+        let estimate =
+            |fen| PstEvaluator::default().evaluate(&Game::from_fen(fen).expect("valid fen"));
+        // with all the pieces on the board the king belongs behind its pawns
+        assert!(
+            estimate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w kq - 0 1")
+                > estimate("rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1R2 w kq - 0 1")
+        );
+        // with nothing else left it belongs in the centre
+        assert!(
+            estimate("4k3/8/8/8/4K3/8/8/8 w - - 0 1") > estimate("4k3/8/8/8/8/8/8/6K1 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn mobility() {
+        let estimate = |evaluator: &dyn Evaluator, fen| {
+            evaluator.evaluate(&Game::from_fen(fen).expect("valid fen"))
+        };
+        // a knight in the centre has eight squares, one in the corner two
+        let developed = "4k3/8/8/8/3N4/8/8/4K3 w - - 0 1";
+        let cornered = "4k3/8/8/8/8/8/8/N3K3 w - - 0 1";
+        let mobility = MobilityEvaluator::default();
+        assert_eq!(estimate(&mobility, developed), 8 * MOBILITY_WEIGHT);
+        assert_eq!(estimate(&mobility, cornered), 2 * MOBILITY_WEIGHT);
+        assert!(
+            estimate(&PstEvaluator::default(), developed)
+                > estimate(&PstEvaluator::default(), cornered)
+        );
+        // whoever's turn it is, and with its own pieces in the way
+        assert_eq!(
+            estimate(&mobility, "n3k3/1p6/2p5/8/8/8/8/4K3 w - - 0 1"),
+            -2 * MOBILITY_WEIGHT
+        );
+        assert_eq!(estimate(&MobilityEvaluator { weight: 0 }, developed), 0);
+    }
+
+    #[test]
+    fn composite() {
+        // white is a knight up
+        let game = Game::from_fen("4k3/p7/8/8/8/8/P7/1N2K3 w - - 0 1").expect("valid fen");
+        let material = MaterialEvaluator.evaluate(&game);
+        assert_eq!(material, 300);
+        let composite = CompositeEvaluator::new(
+            vec![
+                Box::new(MaterialEvaluator),
+                Box::new(PstEvaluator::default()),
+            ],
+            vec![2, -1],
+        );
+        assert_eq!(
+            composite.evaluate(&game),
+            2 * material - PstEvaluator::default().evaluate(&game)
+        );
+    }
+}
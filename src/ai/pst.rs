@@ -0,0 +1,133 @@
+//! Piece-square tables in centipawns, after Tomasz Michniewski's "Simplified Evaluation Function".
+//! Tables are laid out as seen from White's side of the board: the first row is rank 8.
+
+use crate::game::{Piece, PieceColor, PieceType, Position};
+
+#[rustfmt::skip]
+pub const PAWN_TABLE: [i8; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+pub const PAWN_ENDGAME_TABLE: [i8; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    20, 20, 20, 20, 20, 20, 20, 20,
+    10, 10, 10, 10, 10, 10, 10, 10,
+    10, 10, 10, 10, 10, 10, 10, 10,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+pub const KNIGHT_TABLE: [i8; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+pub const BISHOP_TABLE: [i8; 64] = [
+   -20,-10,-10,-10,-10,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5, 10, 10,  5,  0,-10,
+   -10,  5,  5, 10, 10,  5,  5,-10,
+   -10,  0, 10, 10, 10, 10,  0,-10,
+   -10, 10, 10, 10, 10, 10, 10,-10,
+   -10,  5,  0,  0,  0,  0,  5,-10,
+   -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+pub const ROOK_TABLE: [i8; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+pub const QUEEN_TABLE: [i8; 64] = [
+   -20,-10,-10, -5, -5,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5,  5,  5,  5,  0,-10,
+    -5,  0,  5,  5,  5,  5,  0, -5,
+     0,  0,  5,  5,  5,  5,  0, -5,
+   -10,  5,  5,  5,  5,  5,  0,-10,
+   -10,  0,  5,  0,  0,  0,  0,-10,
+   -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+pub const KING_TABLE: [i8; 64] = [
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -20,-30,-30,-40,-40,-30,-30,-20,
+   -10,-20,-20,-20,-20,-20,-20,-10,
+    20, 20,  0,  0,  0,  0, 20, 20,
+    20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+pub const KING_ENDGAME_TABLE: [i8; 64] = [
+   -50,-40,-30,-20,-20,-30,-40,-50,
+   -30,-20,-10,  0,  0,-10,-20,-30,
+   -30,-10, 20, 30, 30, 20,-10,-30,
+   -30,-10, 30, 40, 40, 30,-10,-30,
+   -30,-10, 30, 40, 40, 30,-10,-30,
+   -30,-10, 20, 30, 30, 20,-10,-30,
+   -30,-30,  0,  0,  0,  0,-30,-30,
+   -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+fn midgame_table(piece: PieceType) -> &'static [i8; 64] {
+    match piece {
+        PieceType::Pawn => &PAWN_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Bishop => &BISHOP_TABLE,
+        PieceType::Rook => &ROOK_TABLE,
+        PieceType::Queen => &QUEEN_TABLE,
+        PieceType::King => &KING_TABLE,
+    }
+}
+
+fn endgame_table(piece: PieceType) -> &'static [i8; 64] {
+    match piece {
+        PieceType::Pawn => &PAWN_ENDGAME_TABLE,
+        PieceType::King => &KING_ENDGAME_TABLE,
+        piece => midgame_table(piece),
+    }
+}
+
+/// (midgame, endgame) bonus for `piece` standing on `position`, from its own side's perspective
+pub fn bonus(piece: Piece, position: Position) -> (i32, i32) {
+    // black's tables are white's, mirrored
+    let position = match piece.color {
+        PieceColor::White => position,
+        PieceColor::Black => position.flip_rank(),
+    };
+    let index = ((7 - position.rank()) * 8 + position.file()) as usize;
+    (
+        midgame_table(piece.piece)[index] as i32,
+        endgame_table(piece.piece)[index] as i32,
+    )
+}
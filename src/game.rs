@@ -1,14 +1,45 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    sync::OnceLock,
+};
 
+mod bitboards;
 mod board;
 mod castling;
+mod chess960;
+mod fen;
+mod kings;
+mod r#move;
+mod perft;
+mod pgn;
 pub mod piece;
+mod polyglot;
+mod san;
+mod see;
+#[cfg(feature = "serde")]
+mod serialize;
+mod setup;
+mod zobrist;
 
-pub use board::position::Position;
-use board::{position::Movement, Board};
-use castling::Castling;
+use bitboards::Bitboards;
+pub use board::position::{Movement, ParsePositionError, Position, ALL_POSITIONS};
+pub use board::Board;
+pub use castling::{Castling, CastlingFiles, CastlingInfo};
+pub use fen::FenError;
+use kings::Kings;
+pub use pgn::{Pgn, PgnError};
 pub use piece::{Piece, PieceColor, PieceType};
+pub use r#move::{Move, MoveError, ParseMoveError, Undo};
+pub use setup::PositionError;
+pub(crate) use zobrist::splitmix64;
 
+/// the square named `name`, such as "e4", for tests to refer to squares by
+#[cfg(test)]
+pub(crate) fn square(name: &str) -> Position {
+    name.parse().expect("square name")
+}
+
+#[derive(Clone)]
 pub struct Game {
     turn: PieceColor,
     board: Board,
@@ -16,19 +47,99 @@ pub struct Game {
     just_advanced_two: Option<Position>,
     /// for castling
     castling: Castling,
+    /// derived from `board`
+    kings: Kings,
+    /// derived from `board`
+    bitboards: Bitboards,
+    /// see `attack_table`; every move makes a new `Game`, which starts without one
+    attack_table: OnceLock<Box<AttackTable>>,
+    /// see `hash`
+    hash: u64,
+    /// plies since the last capture or pawn move, for the fifty-move rule
+    halfmove_clock: u32,
+    /// starts at 1 and goes up after each of black's moves
+    fullmove_number: u32,
+}
+
+/// games are equal when their positions are, regardless of whether their attack tables have
+/// been built or how many moves it took to reach them
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.turn == other.turn
+            && self.board == other.board
+            && self.just_advanced_two == other.just_advanced_two
+            && self.castling == other.castling
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+impl Eq for Game {}
+
+/// the squares a piece can move to, as `moves` gives them. With the `fast-alloc` feature these
+/// live on the stack, which saves a heap allocation for each piece whenever moves are listed
+/// by piece, as the TUI and SAN do; a queen in the centre reaches the most squares, 27.
+#[cfg(feature = "fast-alloc")]
+pub type Targets = arrayvec::ArrayVec<Position, 28>;
+#[cfg(not(feature = "fast-alloc"))]
+pub type Targets = Vec<Position>;
+
+/// `table[from][to]` is whether the piece at `from` attacks `to`, indexed by square number
+pub type AttackTable = [[bool; 64]; 64];
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Outcome {
     Win(PieceColor),
-    Draw,
+    Draw(DrawReason),
+    /// the given color resigned, so the other wins
+    Resignation(PieceColor),
+    /// the given color ran out of time, so the other wins
+    Timeout(PieceColor),
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    Stalemate,
+    /// fifty moves by each side without a capture or a pawn move
+    FiftyMoveRule,
+    /// neither side has the pieces to mate
+    InsufficientMaterial,
+    /// the same position for the third time
+    ThreefoldRepetition,
+    /// both players agreed to a draw
+    Agreed,
+}
+
+impl Outcome {
+    /// the color that won, whether by mate, resignation or time, or None for a draw
+    pub fn winner(self) -> Option<PieceColor> {
+        match self {
+            Outcome::Win(color) => Some(color),
+            Outcome::Resignation(color) | Outcome::Timeout(color) => Some(!color),
+            Outcome::Draw(_) => None,
+        }
+    }
 }
 
 impl Display for Outcome {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Outcome::Win(color) => writeln!(f, "{color} wins!"),
-            Outcome::Draw => writeln!(f, "Draw!"),
+            Outcome::Draw(reason) => writeln!(f, "Draw by {reason}!"),
+            Outcome::Resignation(color) => writeln!(f, "{color} resigned. {} wins!", !*color),
+            Outcome::Timeout(color) => writeln!(f, "{color} ran out of time. {} wins!", !*color),
+        }
+    }
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "stalemate"),
+            DrawReason::FiftyMoveRule => write!(f, "the fifty-move rule"),
+            DrawReason::InsufficientMaterial => write!(f, "insufficient material"),
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::Agreed => write!(f, "agreement"),
         }
     }
 }
@@ -46,211 +157,292 @@ impl Game {
         let board = Board::new();
         let just_advanced_two = None;
         let castling = Castling::new();
-        Self {
+        let kings = Kings::find(&board);
+        let bitboards = Bitboards::find(&board);
+        let mut game = Self {
             turn,
             board,
             just_advanced_two,
             castling,
-        }
+            kings,
+            bitboards,
+            attack_table: OnceLock::new(),
+            hash: 0,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        };
+        game.hash = game.zobrist();
+        game
     }
 
     pub fn turn(&self) -> PieceColor {
         self.turn
     }
 
+    /// plies since the last capture or pawn move; the fifty-move rule applies at 100
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// the number of the move being played, counting a move by each side as one
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// `halfmove_clock` and `fullmove_number` once the player to move moves, resetting the
+    /// halfmove clock if the move is a capture or a pawn move
+    fn clocks_after(&self, irreversible: bool) -> (u32, u32) {
+        let halfmove_clock = if irreversible {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        let fullmove_number = match self.turn {
+            PieceColor::White => self.fullmove_number,
+            PieceColor::Black => self.fullmove_number + 1,
+        };
+        (halfmove_clock, fullmove_number)
+    }
+
     pub fn get(&self, position: Position) -> Option<Piece> {
         self.board.get(position)
     }
 
-    /// returns moves that can be made, but without filtering out moves into check
+    /// the square the pawn that has just advanced two passed over, which the player to move
+    /// can capture it on en passant, as FEN records it
+    pub fn en_passant_square(&self) -> Option<Position> {
+        self.just_advanced_two
+            .map(|pawn| pawn.pawn(self.turn).expect("pawn advanced two"))
+    }
+
+    /// the moves the current player can make, grouped by the square they move from; see
+    /// `legal_moves` for them one by one
     /// ENSURES: there is a piece at all keys of the returned map
-    pub fn moves(&self) -> impl Iterator<Item = (Position, Vec<Position>)> + '_ {
-        let king_position = {
-            let king = Piece {
-                piece: PieceType::King,
-                color: self.turn,
-            };
-            self.board.position_of(king).expect("king always exists")
-        };
+    pub fn moves(&self) -> impl Iterator<Item = (Position, Targets)> + '_ {
         self.board
             .iter(self.turn)
-            .map(move |(from, Piece { piece, color })| {
-                debug_assert_eq!(color, self.turn);
-                let does_not_cause_check = |to: Position| {
-                    let after_move = if cfg!(debug_assertions) && self.is_promotion(from, to) {
-                        self.promote(from, to, PieceType::Queen)
-                    } else {
-                        self.r#move(from, to)
-                    };
-                    !after_move.attacks(!color, king_position)
-                };
-                let mut moves = vec![];
-                let mut saturate = |f: &dyn Fn(Position) -> Option<Position>| {
-                    let mut to_opt = f(from);
-                    while let Some(to) = to_opt {
-                        if let Some(other) = self.board.get(to) {
-                            if other.color != color && does_not_cause_check(to) {
-                                moves.push(to);
-                            }
-                            break;
-                        } else if does_not_cause_check(to) {
-                            moves.push(to);
-                        }
-                        to_opt = f(to);
-                    }
-                };
-                match piece {
-                    PieceType::Pawn => {
-                        let forward = from.pawn(color).expect("pawn is never on last rank");
-                        if self.board.is_vacant(forward) && does_not_cause_check(forward) {
-                            moves.push(forward);
-                        }
-
-                        if let Some(forward_two) = forward.pawn(color) {
-                            if from.rank() == color.pawn_starting_rank()
-                                && self.board.is_vacant(forward)
-                                && self.board.is_vacant(forward_two)
-                                && does_not_cause_check(forward_two)
-                            {
-                                moves.push(forward_two);
-                            }
-                        }
-
-                        if let Some(capture_left) = forward.left() {
-                            if (self
-                                .board
-                                .get(capture_left)
-                                .is_some_and(|other| other.color == !color)
-                                || self.just_advanced_two.is_some_and(|position| {
-                                    // en passant
-                                    position == from.left().expect("rectangle")
-                                        && self
-                                            .board
-                                            .get(position)
-                                            .expect("Game::just_advanced_two invariant")
-                                            .color
-                                            == !color
-                                }))
-                                && does_not_cause_check(capture_left)
-                            {
-                                moves.push(capture_left);
-                            }
-                        }
-
-                        if let Some(capture_right) = forward.right() {
-                            if (self
-                                .board
-                                .get(capture_right)
-                                .is_some_and(|other| other.color == !color)
-                                || self.just_advanced_two.is_some_and(|position| {
-                                    // en passant
-                                    position == from.right().expect("rectangle")
-                                        && self
-                                            .board
-                                            .get(position)
-                                            .expect("Game::just_advanced_two invariant")
-                                            .color
-                                            == !color
-                                }))
-                                && does_not_cause_check(capture_right)
-                            {
-                                moves.push(capture_right);
-                            }
-                        }
-                    }
-                    PieceType::Knight => {
-                        let mut try_insert = |to: Option<Position>| {
-                            if let Some(to) = to {
-                                if self.board.get(to).is_none_or(|other| other.color != color)
-                                    && does_not_cause_check(to)
-                                {
-                                    moves.push(to);
-                                }
-                            }
-                        };
-                        try_insert(from.up().up().left());
-                        try_insert(from.up().up().right());
-                        try_insert(from.left().left().up());
-                        try_insert(from.left().left().down());
-                        try_insert(from.down().down().left());
-                        try_insert(from.down().down().right());
-                        try_insert(from.right().right().up());
-                        try_insert(from.right().right().down());
-                    }
-                    PieceType::Bishop => {
-                        saturate(&|p| p.up().left());
-                        saturate(&|p| p.up().right());
-                        saturate(&|p| p.down().left());
-                        saturate(&|p| p.down().right());
+            .map(move |(from, piece)| (from, self.moves_of(from, piece)))
+    }
+
+    /// as `moves`, but without filtering out moves that leave the current player's king in
+    /// check, which is much cheaper. Castling is still only allowed out of, through and into
+    /// squares that are not attacked.
+    /// ENSURES: there is a piece at all keys of the returned map
+    pub fn pseudo_moves(&self) -> impl Iterator<Item = (Position, Targets)> + '_ {
+        self.board
+            .iter(self.turn)
+            .map(move |(from, piece)| (from, self.pseudo_moves_of(from, piece)))
+    }
+
+    /// the moves the current player's piece at `position` can make, or None if the current
+    /// player has no piece there
+    pub fn legal_moves_from(&self, position: Position) -> Option<Targets> {
+        let piece = self
+            .board
+            .get(position)
+            .filter(|piece| piece.color == self.turn)?;
+        Some(self.moves_of(position, piece))
+    }
+
+    /// whether the current player can move at all, stopping at the first piece with a legal
+    /// move and, for that piece, checking no more of its moves once one is legal
+    pub fn has_legal_moves(&self) -> bool {
+        self.board.iter(self.turn).any(|(from, piece)| {
+            let mut legal = false;
+            self.for_each_pseudo_move_of(from, piece, |to| {
+                legal = legal || self.does_not_cause_check(from, to);
+            });
+            legal
+        })
+    }
+
+    /// where the player to move's king stands
+    pub fn king_position(&self) -> Position {
+        self.kings[self.turn]
+    }
+
+    /// the moves that `piece` at `from` can make, as for `moves`
+    fn moves_of(&self, from: Position, piece: Piece) -> Targets {
+        let mut moves = self.pseudo_moves_of(from, piece);
+        moves.retain(|to| self.does_not_cause_check(from, *to));
+        moves
+    }
+
+    /// whether moving the current player's piece at `from` to `to` leaves their king out of
+    /// check.
+    /// REQUIRES: the move is pseudo-legal
+    fn does_not_cause_check(&self, from: Position, to: Position) -> bool {
+        // `castling_move` has already made sure
+        if self.is_castling(from, to) {
+            return true;
+        }
+        let after_move = if cfg!(debug_assertions) && self.is_promotion(from, to) {
+            self.promote(from, to, PieceType::Queen)
+        } else {
+            self.r#move(from, to)
+        };
+        !after_move.attacks(!self.turn, after_move.kings[self.turn])
+    }
+
+    /// the moves that `piece` at `from` can make, as for `pseudo_moves`
+    fn pseudo_moves_of(&self, from: Position, piece: Piece) -> Targets {
+        let mut moves = Targets::new();
+        self.for_each_pseudo_move_of(from, piece, |to| moves.push(to));
+        moves
+    }
+
+    /// calls `f` with where each of the moves that `piece` at `from` can make goes, as for
+    /// `pseudo_moves`, without collecting them
+    fn for_each_pseudo_move_of(&self, from: Position, piece: Piece, mut f: impl FnMut(Position)) {
+        // only pawns, which capture en passant, and the king, which castles, need it to be
+        // their turn
+        debug_assert!(
+            piece.color == self.turn || !matches!(piece.piece, PieceType::Pawn | PieceType::King)
+        );
+        let Piece { piece, color } = piece;
+        let mut saturate = |step: &dyn Fn(Position) -> Option<Position>| {
+            let mut to_opt = step(from);
+            while let Some(to) = to_opt {
+                if let Some(other) = self.board.get(to) {
+                    if other.color != color {
+                        f(to);
                     }
-                    PieceType::Rook => {
-                        saturate(&|p| p.up());
-                        saturate(&|p| p.left());
-                        saturate(&|p| p.down());
-                        saturate(&|p| p.right());
+                    break;
+                }
+                f(to);
+                to_opt = step(to);
+            }
+        };
+        match piece {
+            PieceType::Pawn => {
+                let forward = from.pawn(color).expect("pawn is never on last rank");
+                if self.board.is_vacant(forward) {
+                    f(forward);
+                }
+
+                if let Some(forward_two) = forward.pawn(color) {
+                    if from.rank() == color.pawn_starting_rank()
+                        && self.board.is_vacant(forward)
+                        && self.board.is_vacant(forward_two)
+                    {
+                        f(forward_two);
                     }
-                    PieceType::Queen => {
-                        saturate(&|p| p.up());
-                        saturate(&|p| p.left());
-                        saturate(&|p| p.down());
-                        saturate(&|p| p.right());
-                        saturate(&|p| p.up().left());
-                        saturate(&|p| p.up().right());
-                        saturate(&|p| p.down().left());
-                        saturate(&|p| p.down().right());
+                }
+
+                if let Some(capture_left) = forward.left() {
+                    if self
+                        .board
+                        .get(capture_left)
+                        .is_some_and(|other| other.color == !color)
+                        || self.just_advanced_two.is_some_and(|position| {
+                            // en passant
+                            position == from.left().expect("rectangle")
+                                && self
+                                    .board
+                                    .get(position)
+                                    .expect("Game::just_advanced_two invariant")
+                                    .color
+                                    == !color
+                        })
+                    {
+                        f(capture_left);
                     }
-                    PieceType::King => {
-                        let does_not_cause_check =
-                            |to: Position| !self.r#move(from, to).attacks(!color, to);
-                        let mut try_insert = |to: Option<Position>| {
-                            if let Some(to) = to {
-                                if self.board.get(to).is_none_or(|other| other.color != color)
-                                    && does_not_cause_check(to)
-                                {
-                                    moves.push(to);
-                                }
-                            }
-                        };
-                        try_insert(from.up());
-                        try_insert(from.up().right());
-                        try_insert(from.right());
-                        try_insert(from.down().right());
-                        try_insert(from.down());
-                        try_insert(from.down().left());
-                        try_insert(from.left());
-                        try_insert(from.up().left());
-
-                        if self.castling[color].can_castle_queenside() {
-                            let left = from.left().expect("castling");
-                            let left_left = left.left().expect("castling");
-                            let left_left_left = left_left.left().expect("castling");
-                            if self.board.is_vacant(left)
-                                && self.board.is_vacant(left_left)
-                                && self.board.is_vacant(left_left_left)
-                                && !self.attacks(!color, from)
-                                && !self.attacks(!color, left)
-                                && !self.attacks(!color, left_left)
-                            {
-                                moves.push(left_left);
-                            }
-                        }
-
-                        if self.castling[color].can_castle_kingside() {
-                            let right = from.right().expect("castling");
-                            let right_right = right.right().expect("castling");
-                            if self.board.is_vacant(right)
-                                && self.board.is_vacant(right_right)
-                                && !self.attacks(!color, from)
-                                && !self.attacks(!color, right)
-                                && !self.attacks(!color, right_right)
-                            {
-                                moves.push(right_right);
-                            }
-                        }
+                }
+
+                if let Some(capture_right) = forward.right() {
+                    if self
+                        .board
+                        .get(capture_right)
+                        .is_some_and(|other| other.color == !color)
+                        || self.just_advanced_two.is_some_and(|position| {
+                            // en passant
+                            position == from.right().expect("rectangle")
+                                && self
+                                    .board
+                                    .get(position)
+                                    .expect("Game::just_advanced_two invariant")
+                                    .color
+                                    == !color
+                        })
+                    {
+                        f(capture_right);
                     }
                 }
-                (from, moves)
-            })
+            }
+            PieceType::Knight => from
+                .knight_targets()
+                .filter(|&to| self.board.get(to).is_none_or(|other| other.color != color))
+                .for_each(f),
+            PieceType::Bishop => {
+                saturate(&|p| p.up().left());
+                saturate(&|p| p.up().right());
+                saturate(&|p| p.down().left());
+                saturate(&|p| p.down().right());
+            }
+            PieceType::Rook => {
+                saturate(&|p| p.up());
+                saturate(&|p| p.left());
+                saturate(&|p| p.down());
+                saturate(&|p| p.right());
+            }
+            PieceType::Queen => {
+                saturate(&|p| p.up());
+                saturate(&|p| p.left());
+                saturate(&|p| p.down());
+                saturate(&|p| p.right());
+                saturate(&|p| p.up().left());
+                saturate(&|p| p.up().right());
+                saturate(&|p| p.down().left());
+                saturate(&|p| p.down().right());
+            }
+            PieceType::King => {
+                from.king_targets()
+                    .filter(|&to| self.board.get(to).is_none_or(|other| other.color != color))
+                    .for_each(&mut f);
+                self.castling_move(from, false).into_iter().for_each(&mut f);
+                self.castling_move(from, true).into_iter().for_each(f);
+            }
+        }
+    }
+
+    /// where the king at `from` moves to castle on the given side, if it can: two squares
+    /// towards the rook, or onto the rook in Chess960
+    fn castling_move(&self, from: Position, kingside: bool) -> Option<Position> {
+        let color = self.turn;
+        let rights = self.castling[color];
+        if !(kingside && rights.can_castle_kingside() || !kingside && rights.can_castle_queenside())
+        {
+            return None;
+        }
+        let files = self.castling.files();
+        debug_assert_eq!(from.file(), files.king, "castling rights invariant");
+        let rank = from.rank();
+        let rook_from = files.rook(kingside);
+        let (king_to, rook_to) = if kingside { (6, 5) } else { (2, 3) };
+        let span = |files: [u8; 2]| {
+            let [a, b] = files;
+            (a.min(b)..=a.max(b)).map(move |file| Position::new(rank, file))
+        };
+        // both pieces' paths must be clear of everything but each other
+        let blocked = span([from.file(), king_to])
+            .chain(span([rook_from, rook_to]))
+            .any(|position| {
+                position != from && position.file() != rook_from && !self.board.is_vacant(position)
+            });
+        // and the king may not castle out of, through or into check
+        if blocked || span([from.file(), king_to]).any(|position| self.attacks(!color, position)) {
+            return None;
+        }
+        let to = if self.castling.is_chess960() {
+            Position::new(rank, rook_from)
+        } else {
+            Position::new(rank, king_to)
+        };
+        // in Chess960 the rook may have been shielding the king's destination
+        (!self
+            .r#move(from, to)
+            .attacks(!color, Position::new(rank, king_to)))
+        .then_some(to)
     }
 
     fn sliding_attacks(&self, mut position: Position, target: Position) -> bool {
@@ -270,89 +462,286 @@ impl Game {
         }
     }
 
-    fn attacks(&self, color: PieceColor, target: Position) -> bool {
+    /// whether `piece`, standing at `position`, attacks `target`
+    fn attacks_from(&self, position: Position, piece: Piece, target: Position) -> bool {
+        match piece.piece {
+            PieceType::Pawn => {
+                let pawn_move = position.pawn(piece.color).expect("pawn not on last rank");
+                pawn_move.rank() == target.rank() && pawn_move.file().abs_diff(target.file()) == 1
+            }
+            PieceType::Knight => position.knight_apart(target),
+            PieceType::Bishop => {
+                position.rank().abs_diff(target.rank()) == position.file().abs_diff(target.file())
+                    && self.sliding_attacks(position, target)
+            }
+            PieceType::Rook => {
+                (position.rank() == target.rank() || position.file() == target.file())
+                    && self.sliding_attacks(position, target)
+            }
+            PieceType::Queen => {
+                let d_rank = position.rank().abs_diff(target.rank());
+                let d_file = position.file().abs_diff(target.file());
+                (d_rank == d_file || d_rank == 0 || d_file == 0)
+                    && self.sliding_attacks(position, target)
+            }
+            PieceType::King => position.adjacent(target),
+        }
+    }
+
+    /// whether any of `color`'s pieces attacks `target`, looking knights and pawns up in the
+    /// bitboards and checking the rest against the board
+    pub fn attacks(&self, color: PieceColor, target: Position) -> bool {
+        let attacked =
+            self.bitboards.attacks_from_knights(color) | self.bitboards.attacks_from_pawns(color);
+        attacked & 1 << target.0 != 0
+            || self
+                .board
+                .iter(color)
+                .filter(|(_, piece)| !matches!(piece.piece, PieceType::Knight | PieceType::Pawn))
+                .any(|(position, piece)| self.attacks_from(position, piece, target))
+    }
+
+    /// `attacks`, checking every piece against the board, as it did before there were
+    /// bitboards; kept for comparing the two in tests and benchmarks, and not part of the API
+    #[doc(hidden)]
+    pub fn attacks_by_array(&self, color: PieceColor, target: Position) -> bool {
         self.board
             .iter(color)
-            .any(|(position, piece)| match piece.piece {
-                PieceType::Pawn => {
-                    let pawn_move = position.pawn(color).expect("pawn not on last rank");
-                    pawn_move.rank() == target.rank()
-                        && pawn_move.file().abs_diff(target.file()) == 1
-                }
-                PieceType::Knight => matches!(
-                    (
-                        position.rank().abs_diff(target.rank()),
-                        position.file().abs_diff(target.file()),
-                    ),
-                    (1, 2) | (2, 1)
-                ),
-                PieceType::Bishop => {
-                    position.rank().abs_diff(target.rank())
-                        == position.file().abs_diff(target.file())
-                        && self.sliding_attacks(position, target)
-                }
-                PieceType::Rook => {
-                    (position.rank() == target.rank() || position.file() == target.file())
-                        && self.sliding_attacks(position, target)
+            .any(|(position, piece)| self.attacks_from(position, piece, target))
+    }
+
+    /// which squares each piece attacks, never including its own. Building it takes 64²
+    /// attack queries, but afterwards any number of queries are free; see also `attack_table`.
+    pub fn build_attack_table(&self) -> AttackTable {
+        let mut table = [[false; 64]; 64];
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (from, piece) in self.board.iter(color) {
+                for (to, attacks) in ALL_POSITIONS.into_iter().zip(&mut table[from.0 as usize]) {
+                    // `attacks_from` counts a sliding piece as attacking its own square
+                    *attacks = to != from && self.attacks_from(from, piece, to);
                 }
-                PieceType::Queen => {
-                    let d_rank = position.rank().abs_diff(target.rank());
-                    let d_file = position.file().abs_diff(target.file());
-                    (d_rank == d_file || d_rank == 0 || d_file == 0)
-                        && self.sliding_attacks(position, target)
+            }
+        }
+        table
+    }
+
+    /// `build_attack_table`, built the first time it is needed for this position
+    pub fn attack_table(&self) -> &AttackTable {
+        self.attack_table
+            .get_or_init(|| Box::new(self.build_attack_table()))
+    }
+
+    /// whether any of `color`'s pieces attacks each square, indexed by `Position::0`, from
+    /// `attack_table`
+    pub fn attacked_squares(&self, color: PieceColor) -> [bool; 64] {
+        let table = self.attack_table();
+        let mut attacked = [false; 64];
+        for (from, _) in self.board.iter(color) {
+            for (attacked, &attacks) in attacked.iter_mut().zip(&table[from.0 as usize]) {
+                *attacked |= attacks;
+            }
+        }
+        attacked
+    }
+
+    /// `color`'s pieces that stand alone between their king and one of the opponent's
+    /// bishops, rooks or queens along its line, so moving off the line would expose the king
+    pub fn pinned_pieces(&self, color: PieceColor) -> Vec<Position> {
+        let king = self.kings[color];
+        let mut pinned = Vec::new();
+        for (from, piece) in self.board.iter(!color) {
+            let d_rank = from.rank().abs_diff(king.rank());
+            let d_file = from.file().abs_diff(king.file());
+            let diagonal = d_rank == d_file;
+            let straight = d_rank == 0 || d_file == 0;
+            let aligned = match piece.piece {
+                PieceType::Bishop => diagonal,
+                PieceType::Rook => straight,
+                PieceType::Queen => diagonal || straight,
+                _ => false,
+            };
+            if !aligned {
+                continue;
+            }
+            // walk from the attacker to the king, as in `sliding_attacks`, looking for a single
+            // piece of `color`'s in the way
+            let d_rank = king.rank().cmp(&from.rank()) as i8;
+            let d_file = king.file().cmp(&from.file()) as i8;
+            let mut position = from;
+            let mut blocker = None;
+            loop {
+                position = Position::new(
+                    (position.rank() as i8 + d_rank) as u8,
+                    (position.file() as i8 + d_file) as u8,
+                );
+                if position == king {
+                    pinned.extend(blocker);
+                    break;
                 }
-                PieceType::King => {
-                    position.rank().abs_diff(target.rank()) <= 1
-                        && position.file().abs_diff(target.file()) <= 1
+                match self.board[position] {
+                    None => {}
+                    Some(piece) if piece.color == color && blocker.is_none() => {
+                        blocker = Some(position)
+                    }
+                    Some(_) => break,
                 }
-            })
+            }
+        }
+        pinned
+    }
+
+    /// number of `color`'s pieces attacking `position`, not counting any piece standing on it.
+    /// Only direct attacks count: a piece behind another on the same line (an x-ray) does not.
+    pub fn attackers_count(&self, position: Position, color: PieceColor) -> usize {
+        self.board
+            .iter(color)
+            .filter(|&(from, piece)| from != position && self.attacks_from(from, piece, position))
+            .count()
     }
 
     /// REQUIRES: there is a piece at `from` and move is not a promotion.
     /// If the move is a promotion, use `promote` instead.
     pub fn r#move(&self, from: Position, to: Position) -> Self {
         debug_assert!(!self.is_promotion(from, to), "{from} -> {to}");
+        self.move_unchecked(from, to)
+    }
+
+    fn move_unchecked(&self, from: Position, to: Position) -> Self {
         let piece = self.board.get(from).expect("Game::move precondition");
         let turn = !self.turn;
         let board = self.board.r#move(from, to);
         let just_advanced_two =
-            (piece.piece == PieceType::Pawn && from.rank().abs_diff(to.rank()) == 2).then(|| to);
+            (piece.piece == PieceType::Pawn && from.rank().abs_diff(to.rank()) == 2).then_some(to);
+        let castling = self.castling_after_move(piece, from, to);
+        let kings = self.kings.after_move(&self.board, from, to);
+        let bitboards = self.bitboards.after_move(&self.board, from, to);
+        let hash = self.hash_after_move(from, to);
+        let (halfmove_clock, fullmove_number) =
+            self.clocks_after(piece.piece == PieceType::Pawn || self.is_capture(from, to));
+        Self {
+            turn,
+            board,
+            just_advanced_two,
+            castling,
+            kings,
+            bitboards,
+            attack_table: OnceLock::new(),
+            hash,
+            halfmove_clock,
+            fullmove_number,
+        }
+    }
+
+    /// the position after moving the piece at `from` to `to`, promoting to a queen if it is a
+    /// pawn reaching the last rank, good only for asking what attacks what: castling rights,
+    /// en passant and the move clocks are left as they were, and so is the hash. Like any
+    /// `Game` it lives on the stack and starts without an attack table, so this costs little
+    /// more than copying the board.
+    /// REQUIRES: the move is legal
+    #[inline]
+    fn apply_temp(&self, from: Position, to: Position) -> Self {
+        let mut board = self.board.r#move(from, to);
+        let bitboards = if self.is_promotion(from, to) {
+            board[to] = Some(Piece {
+                piece: PieceType::Queen,
+                color: self.turn,
+            });
+            self.bitboards
+                .after_promotion(&self.board, from, to, PieceType::Queen)
+        } else {
+            self.bitboards.after_move(&self.board, from, to)
+        };
+        Self {
+            turn: !self.turn,
+            board,
+            just_advanced_two: None,
+            castling: self.castling,
+            kings: self.kings.after_move(&self.board, from, to),
+            bitboards,
+            attack_table: OnceLock::new(),
+            hash: self.hash,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+
+    /// whether moving the piece at `from` to `to` puts the opponent in check, promoting to a
+    /// queen if it is a pawn reaching the last rank
+    /// REQUIRES: the move is legal
+    #[inline]
+    pub fn gives_check(&self, from: Position, to: Position) -> bool {
+        self.apply_temp(from, to).check()
+    }
+
+    /// the same position with the other player to move, as if the player to move had passed.
+    /// Not a legal move, but useful to the search.
+    /// REQUIRES: the player to move is not in check
+    pub fn null_move(&self) -> Self {
+        debug_assert!(!self.check());
+        Self {
+            turn: !self.turn,
+            board: self.board,
+            just_advanced_two: None,
+            castling: self.castling,
+            kings: self.kings,
+            bitboards: self.bitboards,
+            attack_table: OnceLock::new(),
+            hash: self.hash_after_null_move(),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+
+    /// the castling rights once `piece` moves from `from` to `to`, which is not a promotion
+    fn castling_after_move(&self, piece: Piece, from: Position, to: Position) -> Castling {
         let mut castling = self.castling;
+        let files = castling.files();
         if from.rank() == piece.color.piece_starting_rank() {
             match (piece.piece, from.file()) {
-                (PieceType::King, 4) => {
+                (PieceType::King, file) if file == files.king => {
                     castling[piece.color].move_king();
                 }
-                (PieceType::Rook, 0) => {
+                (PieceType::Rook, file) if file == files.queenside_rook => {
                     castling[piece.color].move_queenside_rook();
                 }
-                (PieceType::Rook, 7) => {
+                (PieceType::Rook, file) if file == files.kingside_rook => {
                     castling[piece.color].move_kingside_rook();
                 }
                 _ => {}
             }
         }
-        Self {
-            turn,
-            board,
-            just_advanced_two,
-            castling,
-        }
+        forfeit_captured_rook(&mut castling, !piece.color, to);
+        castling
     }
 
     /// REQUIRES: there is a pawn at `from` and move is a promotion.
     pub fn promote(&self, from: Position, to: Position, piece_type: PieceType) -> Self {
         debug_assert!(self.is_promotion(from, to), "{from} -> {to}");
         debug_assert!(PROMOTIONS.contains(&piece_type), "{piece_type:?}");
+        self.promote_unchecked(from, to, piece_type)
+    }
+
+    fn promote_unchecked(&self, from: Position, to: Position, piece_type: PieceType) -> Self {
         let turn = !self.turn;
         let board = self.board.promote(from, to, piece_type);
         let just_advanced_two = None;
-        let castling_info = self.castling.clone();
+        let mut castling_info = self.castling;
+        forfeit_captured_rook(&mut castling_info, turn, to);
+        let hash = self.hash_after(Move::Promote(from, to, piece_type));
+        let (halfmove_clock, fullmove_number) = self.clocks_after(true);
         Self {
             turn,
             board,
             just_advanced_two,
             castling: castling_info,
+            kings: self.kings,
+            bitboards: self
+                .bitboards
+                .after_promotion(&self.board, from, to, piece_type),
+            attack_table: OnceLock::new(),
+            hash,
+            halfmove_clock,
+            fullmove_number,
         }
     }
 
@@ -370,35 +759,158 @@ impl Game {
         )
     }
 
+    /// REQUIRES: there is a piece at `from`
+    pub fn is_capture(&self, from: Position, to: Position) -> bool {
+        let piece = self.board.get(from).expect("Game::is_capture precondition");
+        // in Chess960 the king castles onto its own rook
+        self.board
+            .get(to)
+            .is_some_and(|other| other.color != piece.color)
+            || (piece.piece == PieceType::Pawn && from.file() != to.file())
+    }
+
+    /// whether the move is castling, written as the king moving two squares or, in Chess960,
+    /// onto its own rook.
+    /// REQUIRES: the move is legal
+    pub fn is_castling(&self, from: Position, to: Position) -> bool {
+        self.board.castling(from, to).is_some()
+    }
+
+    /// the subset of `moves` that capture a piece (including en passant) or promote a pawn,
+    /// i.e. the moves that change the material balance
+    pub fn captures(&self) -> impl Iterator<Item = (Position, Targets)> + '_ {
+        self.moves().filter_map(|(from, mut moves)| {
+            moves.retain(|to| self.is_capture(from, *to) || self.is_promotion(from, *to));
+            (!moves.is_empty()).then_some((from, moves))
+        })
+    }
+
     pub fn check(&self) -> bool {
-        let king = Piece {
-            piece: PieceType::King,
-            color: self.turn,
-        };
-        let king_position = self.board.position_of(king).expect("king always exists");
-        self.attacks(!self.turn, king_position)
+        self.attacks(!self.turn, self.king_position())
     }
 
     fn mate(&self) -> bool {
-        self.moves().all(|(_, moves)| moves.is_empty())
+        !self.has_legal_moves()
     }
 
-    /// returns None if the game is still in progress
+    /// whether neither side can mate, however badly the other plays: there are no pawns,
+    /// rooks or queens, and either at most one knight or bishop, or only bishops all on
+    /// squares of one color
+    fn insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        let mut knights = false;
+        // whether there are bishops on dark and on light squares
+        let mut bishops = [false; 2];
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (position, piece) in self.board.iter(color) {
+                match piece.piece {
+                    PieceType::King => continue,
+                    PieceType::Knight => knights = true,
+                    PieceType::Bishop => bishops[position.is_light() as usize] = true,
+                    PieceType::Pawn | PieceType::Rook | PieceType::Queen => return false,
+                }
+                minors += 1;
+            }
+        }
+        minors <= 1 || !(knights || bishops[0] && bishops[1])
+    }
+
+    /// returns None if the game is still in progress.
+    /// Draws by repetition depend on the moves before, which are not kept, so they are up to
+    /// whoever keeps them, and so is calling a draw once `halfmove_clock` reaches 100.
     pub fn status(&self) -> Option<Outcome> {
-        self.mate().then(|| {
-            if self.check() {
+        if self.mate() {
+            Some(if self.check() {
                 // mate is check
                 Outcome::Win(!self.turn)
             } else {
                 // mate is stale
-                Outcome::Draw
-            }
-        })
+                Outcome::Draw(DrawReason::Stalemate)
+            })
+        } else if self.insufficient_material() {
+            Some(Outcome::Draw(DrawReason::InsufficientMaterial))
+        } else {
+            None
+        }
     }
 
     pub fn iter(&self, color: PieceColor) -> impl Iterator<Item = (Position, Piece)> + '_ {
         self.board.iter(color)
     }
+
+    /// number of squares `color`'s knights, bishops, rooks and queens can move to, counting
+    /// each piece's squares separately and ignoring pins and checks, whoever's turn it is
+    pub fn mobility(&self, color: PieceColor) -> usize {
+        let mut squares = 0;
+        for (from, piece) in self.board.iter(color) {
+            if !matches!(piece.piece, PieceType::Pawn | PieceType::King) {
+                self.for_each_pseudo_move_of(from, piece, |_| squares += 1);
+            }
+        }
+        squares
+    }
+
+    /// number of pawns in the longest diagonal chain of `color`'s pawns,
+    /// in which each pawn defends the next; 0 if `color` has no pawns
+    pub fn longest_pawn_chain(&self, color: PieceColor) -> usize {
+        let pawn = Some(Piece {
+            piece: PieceType::Pawn,
+            color,
+        });
+        // chain[square] = length of the longest chain ending with the pawn on that square.
+        // Walking from the back of the board to the front fills in a pawn's defenders before it.
+        let mut chain = [0; 64];
+        let mut ranks = [0, 1, 2, 3, 4, 5, 6, 7];
+        if color == PieceColor::Black {
+            ranks.reverse();
+        }
+        let mut longest = 0;
+        for rank in ranks {
+            for file in 0..8 {
+                let position = Position::new(rank, file);
+                if self.board.get(position) != pawn {
+                    continue;
+                }
+                let behind = position.pawn(!color);
+                let length = 1 + [behind.left(), behind.right()]
+                    .into_iter()
+                    .flatten()
+                    .map(|defender| chain[defender.0 as usize])
+                    .max()
+                    .unwrap_or(0);
+                chain[position.0 as usize] = length;
+                longest = longest.max(length);
+            }
+        }
+        longest
+    }
+
+    /// every unordered pair of `color`'s pawns as `(file, rank)` coordinates
+    pub fn iter_pawn_pairs(
+        &self,
+        color: PieceColor,
+    ) -> impl Iterator<Item = ((u8, u8), (u8, u8))> + '_ {
+        self.board.iter_pawn_pairs(color)
+    }
+}
+
+/// a rook captured on its starting square can no longer castle.
+/// `color` is the side that may have lost a rook by a move to `to`.
+fn forfeit_captured_rook(castling: &mut Castling, color: PieceColor, to: Position) {
+    let files = castling.files();
+    if to.rank() == color.piece_starting_rank() {
+        match to.file() {
+            file if file == files.queenside_rook => castling[color].move_queenside_rook(),
+            file if file == files.kingside_rook => castling[color].move_kingside_rook(),
+            _ => {}
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Debug for Game {
@@ -410,7 +922,12 @@ impl Debug for Game {
 
 #[cfg(test)]
 mod test {
-    use super::{piece::PieceType, Game};
+    use super::{
+        piece::{PieceColor, PieceType},
+        Bitboards, Board, Castling, CastlingInfo, DrawReason, Game, Kings, Move, MoveError,
+        Outcome, ParseMoveError, Piece, Position, PositionError, Targets, ALL_POSITIONS,
+    };
+    use crate::game::square;
 
     #[test]
     fn moves_and_attacks_are_consistent() {
@@ -420,6 +937,7 @@ mod test {
             }
             for (from, moves) in game.moves() {
                 assert!(game.get(from).is_some_and(|piece| piece.color == game.turn));
+                assert_eq!(game.legal_moves_from(from).as_ref(), Some(&moves));
                 for to in moves {
                     if let Some(capture) = game.get(to) {
                         assert_eq!(capture.color, !game.turn, "capture of wrong color");
@@ -436,4 +954,818 @@ mod test {
         }
         rec(&Game::new(), 4)
     }
+
+    #[test]
+    fn legal_moves_from() {
+        let game = Game::new();
+        assert_eq!(
+            game.legal_moves_from(square("g1")).as_deref(),
+            Some(&[square("f3"), square("h3")][..])
+        );
+        assert_eq!(
+            game.legal_moves_from(square("a1")).as_deref(),
+            Some(&[][..])
+        );
+        // empty, and the opponent's
+        assert_eq!(game.legal_moves_from(square("e4")), None);
+        assert_eq!(game.legal_moves_from(square("e7")), None);
+        assert!(game.has_legal_moves());
+        // stalemate
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").expect("valid fen");
+        assert!(!game.has_legal_moves());
+        assert_eq!(
+            game.legal_moves_from(square("h8")).as_deref(),
+            Some(&[][..])
+        );
+    }
+
+    #[test]
+    fn pseudo_moves() {
+        // the bishop is pinned, and the king may not step onto the other rook's file
+        let game = Game::from_fen("k2rr3/8/8/8/8/8/4B3/4K3 w - - 0 1").expect("valid fen");
+        let pseudo_moves: Vec<_> = game.pseudo_moves().collect();
+        let moves: Vec<_> = game.moves().collect();
+        let of = |moves: &[(Position, Targets)], from: &str| {
+            moves
+                .iter()
+                .find(|&&(position, _)| position == square(from))
+                .map(|(_, moves)| moves.clone())
+                .expect("piece")
+        };
+        assert_eq!(of(&pseudo_moves, "e2").len(), 9);
+        assert!(of(&moves, "e2").is_empty());
+        assert!(of(&pseudo_moves, "e1").contains(&square("d1")));
+        assert!(!of(&moves, "e1").contains(&square("d1")));
+        assert!(of(&moves, "e1").contains(&square("f1")));
+    }
+
+    #[test]
+    fn pawn_pairs() {
+        let game = Game::new();
+        assert_eq!(game.iter_pawn_pairs(PieceColor::White).count(), 28);
+        assert!(game.iter_pawn_pairs(PieceColor::Black).all(
+            |((a_file, a_rank), (b_file, b_rank))| a_rank == 6 && b_rank == 6 && a_file < b_file
+        ));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.iter_pawn_pairs(PieceColor::White).count(), 0);
+    }
+
+    #[test]
+    fn position_from_str() {
+        assert_eq!("e4".parse(), Ok(Position::new(3, 4)));
+        assert_eq!(Position::try_from("a1"), Ok(Position::new(0, 0)));
+        assert_eq!(
+            "h8".parse::<Position>().map(|p| p.to_string()),
+            Ok("h8".into())
+        );
+        for invalid in ["", "e", "e44", "i1", "a9", "a0", "4e", "é4"] {
+            assert!(invalid.parse::<Position>().is_err(), "{invalid}");
+        }
+        // every square, in either case
+        for file in "abcdefghABCDEFGH".chars() {
+            for rank in "12345678".chars() {
+                let s = format!("{file}{rank}");
+                assert_eq!(s.parse::<Position>().unwrap().to_string(), s.to_lowercase());
+            }
+        }
+    }
+
+    #[test]
+    fn all_positions() {
+        assert_eq!(Position::all().count(), 64);
+        let names: Vec<String> = Position::all().map(|p| p.to_string()).collect();
+        assert_eq!(names[..3], ["a1", "b1", "c1"]);
+        assert_eq!(names[63], "h8");
+        for (i, name) in names.iter().enumerate() {
+            let position: Position = name.parse().unwrap();
+            assert_eq!(position, ALL_POSITIONS[i]);
+            assert_eq!(position.0 as usize, i);
+            assert_eq!(
+                *name,
+                format!("{}{}", (b'a' + (i % 8) as u8) as char, i / 8 + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn attackers_count() {
+        let game = Game::from_fen("4k3/8/8/3p4/2P1N3/8/8/3RK3 w - - 0 1").expect("valid fen");
+        let d5 = "d5".parse().unwrap();
+        // the c4 pawn and the d1 rook, but not the e4 knight
+        assert_eq!(game.attackers_count(d5, PieceColor::White), 2);
+        assert_eq!(game.attackers_count(d5, PieceColor::Black), 0);
+        let e4 = "e4".parse().unwrap();
+        assert_eq!(game.attackers_count(e4, PieceColor::Black), 1);
+        // the king defends the rook on d1, which does not attack its own square
+        let d1 = "d1".parse().unwrap();
+        assert_eq!(game.attackers_count(d1, PieceColor::White), 1);
+    }
+
+    #[test]
+    fn san() {
+        let san = |fen, from, to, promotion| {
+            let game = Game::from_fen(fen).expect("valid fen");
+            game.san(Move::new(square(from), square(to), promotion))
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "e2", "e4", None), "e4");
+        assert_eq!(san(start, "g1", "f3", None), "Nf3");
+        assert_eq!(
+            san("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1", "a1", "d1", None),
+            "Rad1"
+        );
+        assert_eq!(
+            san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1", "a3", None),
+            "R1a3"
+        );
+        let castling = "r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1";
+        assert_eq!(san(castling, "e8", "g8", None), "O-O");
+        assert_eq!(san(castling, "e8", "c8", None), "O-O-O");
+        assert_eq!(
+            san(
+                "4k3/P7/8/8/8/8/8/4K3 w - - 0 1",
+                "a7",
+                "a8",
+                Some(PieceType::Queen)
+            ),
+            "a8=Q+"
+        );
+        assert_eq!(
+            san(
+                "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+                "f3",
+                "f7",
+                None
+            ),
+            "Qxf7#"
+        );
+    }
+
+    #[test]
+    fn attack_table() {
+        let game = Game::new();
+        let index = |s: &str| square(s).0 as usize;
+        let table = game.attack_table();
+        assert!(table[index("g1")][index("f3")]);
+        assert!(table[index("g1")][index("e2")]);
+        assert!(!table[index("g1")][index("g3")]);
+        // pawns attack diagonally, not forward
+        assert!(table[index("e7")][index("d6")]);
+        assert!(!table[index("e7")][index("e6")]);
+        // an empty square attacks nothing
+        assert!(table[index("e4")].iter().all(|&attacks| !attacks));
+        assert_eq!(*table, game.build_attack_table());
+        // a rook alone on its rank and file attacks fourteen squares, not its own
+        let game = Game::from_fen("7k/8/8/8/3R4/8/8/K7 w - - 0 1").expect("valid fen");
+        let rook = &game.attack_table()[index("d4")];
+        assert!(!rook[index("d4")]);
+        assert_eq!(rook.iter().filter(|&&attacks| attacks).count(), 14);
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        let pinned = |fen, color| Game::from_fen(fen).expect("valid fen").pinned_pieces(color);
+        // the bishop pins the knight to the king along the long diagonal
+        let fen = "8/6k1/5n2/8/8/8/1B6/4K3 w - - 0 1";
+        assert_eq!(pinned(fen, PieceColor::Black), vec![square("f6")]);
+        // but not with another black piece in the way too
+        let fen = "8/6k1/5n2/4b3/8/8/1B6/4K3 w - - 0 1";
+        assert_eq!(pinned(fen, PieceColor::Black), vec![]);
+        // nor with a white one
+        let fen = "8/6k1/5n2/4P3/8/8/1B6/4K3 w - - 0 1";
+        assert_eq!(pinned(fen, PieceColor::Black), vec![]);
+        // the rook pins the pawn, but not two pieces at once
+        let fen = "4r3/6k1/8/8/8/8/4P3/4K3 w - - 0 1";
+        assert_eq!(pinned(fen, PieceColor::White), vec![square("e2")]);
+        let fen = "4r3/6k1/8/8/8/4B3/4P3/4K3 w - - 0 1";
+        assert_eq!(pinned(fen, PieceColor::White), vec![]);
+        assert_eq!(Game::new().pinned_pieces(PieceColor::White), vec![]);
+    }
+
+    #[test]
+    fn attacked_squares() {
+        let game = Game::new();
+        let white = game.attacked_squares(PieceColor::White);
+        for position in ALL_POSITIONS {
+            let attacked = white[position.0 as usize];
+            match position.rank() {
+                2 => assert!(attacked, "{position}"),
+                3.. => assert!(!attacked, "{position}"),
+                _ => {}
+            }
+        }
+        // the queen defends the king, but nothing defends the rooks in the corners
+        assert!(white[Position::new(0, 4).0 as usize]);
+        assert!(!white[Position::new(0, 0).0 as usize]);
+        let black = game.attacked_squares(PieceColor::Black);
+        assert!(black[Position::new(5, 0).0 as usize]);
+        assert!(!black[Position::new(2, 0).0 as usize]);
+    }
+
+    #[test]
+    fn longest_pawn_chain() {
+        assert_eq!(Game::new().longest_pawn_chain(PieceColor::White), 1);
+        // b2-c3-d4-e5 with a branch c3-b4
+        let game = Game::from_fen("4k3/8/8/4P3/1P1P4/2P5/1P6/4K3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.longest_pawn_chain(PieceColor::White), 4);
+        assert_eq!(game.longest_pawn_chain(PieceColor::Black), 0);
+        // black's chains run down the board
+        let game = Game::from_fen("4k3/5p2/4p3/3p4/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.longest_pawn_chain(PieceColor::Black), 3);
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(
+            format!("{:?}", Game::new()),
+            "White to move
+8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+6 · · · · · · · ·
+5 · · · · · · · ·
+4 · · · · · · · ·
+3 · · · · · · · ·
+2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙
+1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+  a b c d e f g h"
+        );
+    }
+
+    #[test]
+    fn perft() {
+        let game = Game::new();
+        assert_eq!(game.perft(1), 20);
+        assert_eq!(game.perft(2), 400);
+        assert_eq!(game.perft(3), 8902);
+        assert_eq!(game.perft_parallel(3), 8902);
+        assert_eq!(game.perft_parallel(4), 197281);
+        let split = game.perft_split(3);
+        assert_eq!(split.len(), 20);
+        assert_eq!(split.iter().map(|&(_, count)| count).sum::<u64>(), 8902);
+
+        // capturing a rook on its starting square takes away that side's castling
+        let game = Game::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8")
+            .expect("valid fen");
+        assert_eq!(game.perft_parallel(3), 62379);
+
+        // "Kiwipete", full of castling, en passant and promotion edge cases
+        let game =
+            Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .expect("valid fen");
+        assert_eq!(game.perft_parallel(3), 97862);
+        // en passant that would expose the king along the rank
+        let game = Game::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").expect("valid fen");
+        assert_eq!(game.perft_parallel(4), 43238);
+    }
+
+    #[test]
+    fn legal_move_count() {
+        fn rec(game: &Game, depth: usize) {
+            let moves = game.legal_moves();
+            assert_eq!(game.legal_move_count(), moves.len(), "{game:?}");
+            assert_eq!(game.has_legal_moves(), !moves.is_empty(), "{game:?}");
+            if depth == 0 {
+                return;
+            }
+            for r#move in moves {
+                rec(&game.apply_move_unchecked(r#move), depth - 1);
+            }
+        }
+        rec(&Game::new(), 3);
+        for fen in [
+            // promotions, each counting four times
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            // checkmate and stalemate
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        ] {
+            rec(&Game::from_fen(fen).expect("valid fen"), 2);
+        }
+    }
+
+    #[test]
+    fn board_hash() {
+        use std::collections::HashSet;
+
+        let start = Game::new();
+        let e4 = start.r#move("e2".parse().unwrap(), "e4".parse().unwrap());
+        // the same placement reached by a different move order
+        let nf3_nc6 = |game: &Game| {
+            game.r#move("g1".parse().unwrap(), "f3".parse().unwrap())
+                .r#move("b8".parse().unwrap(), "c6".parse().unwrap())
+        };
+        let boards: HashSet<Board> = [
+            start.board,
+            e4.board,
+            nf3_nc6(&e4).board,
+            nf3_nc6(&start)
+                .r#move("e2".parse().unwrap(), "e4".parse().unwrap())
+                .board,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(boards.len(), 3);
+    }
+
+    #[test]
+    fn zobrist() {
+        assert_eq!(Game::new().hash(), 17234431138423775338);
+        fn rec(game: &mut Game, depth: usize) {
+            assert_eq!(game.hash(), game.zobrist());
+            if depth == 0 {
+                return;
+            }
+            for r#move in game.legal_moves() {
+                let after = game.apply_move_unchecked(r#move);
+                assert_eq!(game.hash_after(r#move), after.hash(), "{move}");
+                let undo = game.make(r#move);
+                assert_eq!(game.hash(), after.hash(), "{move}");
+                rec(game, depth - 1);
+                game.unmake(undo);
+            }
+        }
+        rec(&mut Game::new(), 3);
+        // castling both ways, en passant and captures of rooks that could castle
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/8/8/8/3pPp2/8/8/R3K1NR b KQkq e3 0 1",
+            // promotions, some capturing a rook that could castle
+            "r3k2r/1P6/8/8/8/8/6p1/R3K2R w KQkq - 0 1",
+        ] {
+            rec(&mut Game::from_fen(fen).expect("valid fen"), 2);
+        }
+        let game = Game::new();
+        assert_eq!(game.null_move().hash(), game.null_move().zobrist());
+    }
+
+    #[test]
+    fn kings() {
+        fn rec(game: &Game, depth: usize) {
+            assert_eq!(game.kings, Kings::find(&game.board), "{game:?}");
+            if depth == 0 {
+                return;
+            }
+            for r#move in game.legal_moves() {
+                rec(&game.apply_move_unchecked(r#move), depth - 1);
+            }
+        }
+        // castling both ways, in standard chess and in Chess960
+        for game in [
+            Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .expect("valid fen"),
+            Game::from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1").expect("valid fen"),
+        ] {
+            rec(&game, 2);
+        }
+    }
+
+    #[test]
+    fn bitboards() {
+        fn rec(game: &Game, depth: usize) {
+            assert_eq!(game.bitboards, Bitboards::find(&game.board), "{game:?}");
+            for color in [PieceColor::White, PieceColor::Black] {
+                for target in ALL_POSITIONS {
+                    assert_eq!(
+                        game.attacks(color, target),
+                        game.attacks_by_array(color, target),
+                        "{color} {target}\n{game:?}"
+                    );
+                }
+            }
+            if depth == 0 {
+                return;
+            }
+            for r#move in game.legal_moves() {
+                rec(&game.apply_move_unchecked(r#move), depth - 1);
+            }
+        }
+        // castling both ways, in standard chess and in Chess960, en passant and promotions
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1",
+            "r3k2r/8/8/8/3pPp2/8/8/R3K1NR b KQkq e3 0 1",
+            "r3k2r/1P6/8/8/8/8/6p1/R3K2R w KQkq - 0 1",
+        ] {
+            rec(&Game::from_fen(fen).expect("valid fen"), 2);
+        }
+    }
+
+    #[test]
+    fn make_unmake() {
+        fn rec(game: &mut Game, depth: usize) {
+            if depth == 0 {
+                return;
+            }
+            let before = game.clone();
+            for r#move in game.legal_moves() {
+                let after = game.apply_move_unchecked(r#move);
+                let undo = game.make(r#move);
+                assert_eq!(*game, after, "{move}");
+                assert_eq!(game.kings, after.kings, "{move}");
+                assert_eq!(game.bitboards, after.bitboards, "{move}");
+                rec(game, depth - 1);
+                game.unmake(undo);
+                assert_eq!(*game, before, "{move}");
+                assert_eq!(game.kings, before.kings, "{move}");
+                assert_eq!(game.bitboards, before.bitboards, "{move}");
+            }
+        }
+        // castling both ways, in standard chess and in Chess960, en passant and promotions
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/8/8/8/3pPp2/8/8/R3K1NR b KQkq e3 0 1",
+            "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            rec(&mut Game::from_fen(fen).expect("valid fen"), 2);
+        }
+    }
+
+    #[test]
+    fn null_move() {
+        let game = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 1")
+            .expect("valid fen");
+        assert_eq!(
+            game.null_move().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        // passing twice forfeits only the chance to capture en passant
+        assert!(game.null_move().null_move() != game);
+        assert_eq!(
+            game.null_move().null_move().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let game = Game::new();
+        assert!(game
+            .apply(Move::Normal(square("e2"), square("e4")))
+            .is_some());
+        assert!(game
+            .apply(Move::Normal(square("e2"), square("e5")))
+            .is_none());
+        // a promotion must name its piece, and only a promotion may
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        assert!(game
+            .apply(Move::Normal(square("a7"), square("a8")))
+            .is_none());
+        assert!(game
+            .apply(Move::Promote(square("a7"), square("a8"), PieceType::King))
+            .is_none());
+        assert!(game
+            .apply(Move::Promote(square("a7"), square("a8"), PieceType::Knight))
+            .is_some());
+        assert!(game
+            .apply(Move::Promote(square("e1"), square("e2"), PieceType::Queen))
+            .is_none());
+    }
+
+    #[test]
+    fn try_apply() {
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        let (a7, a8) = (square("a7"), square("a8"));
+        assert_eq!(
+            game.try_apply(Move::Normal(square("e8"), square("e7"))),
+            Err(MoveError::NoPieceAtSource(square("e8")))
+        );
+        assert_eq!(
+            game.try_apply(Move::Normal(a7, square("a6"))),
+            Err(MoveError::IllegalMove(Move::Normal(a7, square("a6"))))
+        );
+        assert_eq!(
+            game.try_apply(Move::Normal(a7, a8)),
+            Err(MoveError::MissingPromotion(Move::Normal(a7, a8)))
+        );
+        let king_to_queen = Move::Promote(square("e1"), square("e2"), PieceType::Queen);
+        assert_eq!(
+            game.try_apply(king_to_queen),
+            Err(MoveError::NotAPromotion(king_to_queen))
+        );
+        assert_eq!(
+            game.try_apply(Move::Promote(a7, a8, PieceType::Rook))
+                .map(|game| game.to_fen()),
+            Ok("R3k3/8/8/8/8/8/8/4K3 b - - 0 1".to_string())
+        );
+    }
+
+    #[test]
+    fn en_passant_square() {
+        let game = Game::new().r#move(square("e2"), square("e4"));
+        assert_eq!(game.en_passant_square(), Some(square("e3")));
+        let game = game.r#move(square("g8"), square("f6"));
+        assert_eq!(game.en_passant_square(), None);
+        let game = game.r#move(square("e4"), square("e5"));
+        let game = game.r#move(square("d7"), square("d5"));
+        assert_eq!(game.en_passant_square(), Some(square("d6")));
+    }
+
+    #[test]
+    fn gives_check() {
+        // the bishop uncovers the rook's check wherever it goes
+        let game = Game::from_fen("4k3/8/8/8/4B3/8/8/4RK2 w - - 0 1").expect("valid fen");
+        assert!(game.gives_check(square("e4"), square("b7")));
+        assert!(game.gives_check(square("e4"), square("d5")));
+        assert!(!game.gives_check(square("e1"), square("e2")));
+        // promoting with check along the back rank
+        let game = Game::from_fen("k7/6P1/8/8/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        assert!(game.gives_check(square("g7"), square("g8")));
+    }
+
+    #[test]
+    fn see() {
+        // Rxe5 Rxe5 Qxe5: the queen behind the rook recaptures, winning the knight
+        let game = Game::from_fen("k3r3/8/8/4n3/8/8/4R3/4Q2K w - - 0 1").expect("valid fen");
+        assert_eq!(game.see(square("e2"), square("e5")), 300);
+        // without the queen, white loses the rook for the knight
+        let game = Game::from_fen("k3r3/8/8/4n3/8/8/4R3/7K w - - 0 1").expect("valid fen");
+        assert_eq!(game.see(square("e2"), square("e5")), -200);
+        // black's rook declines to recapture where a pawn would take it
+        let game = Game::from_fen("k3r3/8/8/4p3/5P2/8/4R3/7K w - - 0 1").expect("valid fen");
+        assert_eq!(game.see(square("e2"), square("e5")), 100);
+        // the king cannot recapture a defended piece
+        let game = Game::from_fen("8/8/3k4/4p3/8/8/4R3/4R2K w - - 0 1").expect("valid fen");
+        assert_eq!(game.see(square("e2"), square("e5")), 100);
+        assert_eq!(game.see_on(square("e5")), 100);
+        assert_eq!(game.see_on(square("e2")), 0);
+
+        // the queen takes a pawn defended by a pawn, and the pawn is all white wins for it
+        let game = Game::from_fen("k7/8/5p2/4p3/8/8/8/K3Q3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.see(square("e1"), square("e5")), -800);
+        assert_eq!(game.see_on(square("e5")), 0);
+        // and an undefended pawn is free
+        let game = Game::from_fen("k7/8/8/4p3/8/8/8/K3Q3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.see_on(square("e5")), 100);
+        // the pawn takes first, so the exchange pays
+        let game = Game::from_fen("k7/8/5p2/4n3/3P4/8/8/K3Q3 w - - 0 1").expect("valid fen");
+        assert_eq!(game.see_on(square("e5")), 300 - 100 + 100);
+    }
+
+    #[test]
+    fn legal_moves() {
+        assert_eq!(Game::new().legal_moves().len(), 20);
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        let promotions: Vec<Move> = game
+            .legal_moves()
+            .into_iter()
+            .filter(|r#move| r#move.from().to_string() == "a7")
+            .collect();
+        assert_eq!(promotions.len(), 4);
+        assert!(promotions.iter().all(|r#move| r#move.promotion().is_some()));
+        assert!(promotions.iter().all(|&r#move| game.is_legal(r#move)));
+    }
+
+    #[test]
+    fn equality() {
+        let play = |game: Game, moves: &[(&str, &str)]| {
+            moves.iter().fold(game, |game, &(from, to)| {
+                game.r#move(square(from), square(to))
+            })
+        };
+        let open = play(Game::new(), &[("e2", "e3"), ("e7", "e6")]);
+        let knights_back = play(
+            open.clone(),
+            &[("g1", "f3"), ("b8", "c6"), ("f3", "g1"), ("c6", "b8")],
+        );
+        open.attack_table();
+        assert!(open == knights_back);
+        // the same squares, but without castling rights
+        let kings_back = play(
+            open.clone(),
+            &[("e1", "e2"), ("e8", "e7"), ("e2", "e1"), ("e7", "e8")],
+        );
+        assert!(open != kings_back);
+        // the same squares, but black may capture en passant
+        let from_fen = |fen| Game::from_fen(fen).expect("valid fen");
+        assert!(
+            from_fen("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1")
+                != from_fen("4k3/8/8/8/3Pp3/8/8/4K3 b - - 0 1")
+        );
+        // rooks that have both moved lose the rights just as the king moving would
+        assert!(
+            play(
+                from_fen("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1"),
+                &[
+                    ("a8", "b8"),
+                    ("e1", "e2"),
+                    ("h8", "g8"),
+                    ("e2", "e1"),
+                    ("b8", "a8"),
+                    ("e1", "e2"),
+                    ("g8", "h8")
+                ]
+            ) == from_fen("r3k2r/8/8/8/8/8/4K3/8 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn uci_notation() {
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").expect("valid fen");
+        let promotion = Move::Promote(square("a7"), square("a8"), PieceType::Knight);
+        assert_eq!(promotion.to_string(), "a7a8n");
+        assert_eq!(Move::from_uci_str("a7a8n", &game), Ok(promotion));
+        assert_eq!(Move::Normal(square("e1"), square("d2")).to_string(), "e1d2");
+        assert_eq!(
+            Move::from_uci_str("e1e3", &game),
+            Err(ParseMoveError::Illegal(Move::Normal(
+                square("e1"),
+                square("e3")
+            )))
+        );
+        for malformed in ["", "e1", "e1e9", "a7a8N", "a7a8qq"] {
+            assert_eq!(
+                Move::from_uci_str(malformed, &game),
+                Err(ParseMoveError::Malformed(malformed.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn from_position() {
+        let place = |pieces: &[(&str, PieceColor, PieceType)]| {
+            let mut board = Board::new_empty();
+            for &(position, color, piece) in pieces {
+                board[square(position)] = Some(Piece { color, piece });
+            }
+            board
+        };
+        let kings = [
+            ("e1", PieceColor::White, PieceType::King),
+            ("e8", PieceColor::Black, PieceType::King),
+        ];
+        let none = || {
+            let mut castling = Castling::new();
+            castling[PieceColor::White] = CastlingInfo::KingHasMoved;
+            castling[PieceColor::Black] = CastlingInfo::KingHasMoved;
+            castling
+        };
+        let setup = |pieces: &[_], castling, en_passant| {
+            let board = place(&[&kings[..], pieces].concat());
+            Game::from_position(board, PieceColor::White, castling, en_passant, 0, 1)
+        };
+
+        assert_eq!(
+            Game::from_position(Board::new(), PieceColor::White, Castling::new(), None, 0, 1),
+            Ok(Game::new())
+        );
+        assert_eq!(
+            Game::from_position(place(&kings[..1]), PieceColor::White, none(), None, 0, 1),
+            Err(PositionError::KingCount(PieceColor::Black))
+        );
+        assert_eq!(
+            setup(&[("c8", PieceColor::White, PieceType::Pawn)], none(), None),
+            Err(PositionError::PawnOnBackRank(square("c8")))
+        );
+        let knights: Vec<_> = ["a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3"]
+            .into_iter()
+            .map(|position| (position, PieceColor::Black, PieceType::Knight))
+            .collect();
+        assert_eq!(
+            setup(&knights, none(), None).map(drop),
+            Ok(()),
+            "six promoted knights"
+        );
+        let pawns: Vec<_> = ["a7", "b7", "c7"]
+            .into_iter()
+            .map(|position| (position, PieceColor::Black, PieceType::Pawn))
+            .collect();
+        assert_eq!(
+            setup(&[&knights[..], &pawns].concat(), none(), None),
+            Err(PositionError::TooManyPieces(PieceColor::Black))
+        );
+        assert_eq!(
+            setup(&[], Castling::new(), None),
+            Err(PositionError::CastlingRights(PieceColor::White))
+        );
+        let rooks = [
+            ("a1", PieceColor::White, PieceType::Rook),
+            ("h1", PieceColor::White, PieceType::Rook),
+            ("a8", PieceColor::Black, PieceType::Rook),
+            ("h8", PieceColor::Black, PieceType::Rook),
+        ];
+        assert_eq!(setup(&rooks, Castling::new(), None).map(drop), Ok(()));
+        let black_pawn = [("d5", PieceColor::Black, PieceType::Pawn)];
+        assert_eq!(
+            setup(&black_pawn, none(), Some(square("d6"))).map(|game| game.just_advanced_two),
+            Ok(Some(square("d5")))
+        );
+        assert_eq!(
+            setup(&black_pawn, none(), Some(square("e6"))),
+            Err(PositionError::EnPassant(square("e6")))
+        );
+        assert_eq!(
+            setup(&[("e5", PieceColor::White, PieceType::Rook)], none(), None),
+            Err(PositionError::OpponentInCheck(PieceColor::Black))
+        );
+
+        let clocks = |turn, en_passant, halfmove_clock, fullmove_number| {
+            let board = place(&[&kings[..], &black_pawn].concat());
+            Game::from_position(
+                board,
+                turn,
+                none(),
+                en_passant,
+                halfmove_clock,
+                fullmove_number,
+            )
+        };
+        let game = clocks(PieceColor::White, None, 30, 40).expect("valid clocks");
+        assert_eq!((game.halfmove_clock(), game.fullmove_number()), (30, 40));
+        assert_eq!(
+            clocks(PieceColor::White, None, 0, 0),
+            Err(PositionError::FullmoveNumber)
+        );
+        // positions set up in an editor often count more plies than the fullmove number allows
+        assert!(clocks(PieceColor::White, None, 20, 1).is_ok());
+        // a pawn that has just advanced two resets the clock
+        assert_eq!(
+            clocks(PieceColor::White, Some(square("d6")), 1, 3),
+            Err(PositionError::HalfmoveClock(1))
+        );
+    }
+
+    #[test]
+    fn move_clocks() {
+        let game = Game::from_fen("4k3/8/8/3p4/8/8/8/R3K3 w Q - 7 20").expect("valid fen");
+        assert_eq!((game.halfmove_clock(), game.fullmove_number()), (7, 20));
+        // a quiet move by white counts a ply, and the reply by black finishes the move
+        let game = game
+            .apply(Move::Normal(square("a1"), square("a5")))
+            .unwrap();
+        assert_eq!((game.halfmove_clock(), game.fullmove_number()), (8, 20));
+        let game = game
+            .apply(Move::Normal(square("e8"), square("e7")))
+            .unwrap();
+        assert_eq!((game.halfmove_clock(), game.fullmove_number()), (9, 21));
+        assert_eq!(game.to_fen(), "8/4k3/8/R2p4/8/8/8/4K3 w - - 9 21");
+        // a capture resets the clock, and so does a pawn move
+        let mut captured = game
+            .apply(Move::Normal(square("a5"), square("d5")))
+            .unwrap();
+        assert_eq!(captured.halfmove_clock(), 0);
+        let pushed = game
+            .apply(Move::Normal(square("e1"), square("e2")))
+            .unwrap();
+        let pushed = pushed
+            .apply(Move::Normal(square("d5"), square("d4")))
+            .unwrap();
+        assert_eq!((pushed.halfmove_clock(), pushed.fullmove_number()), (0, 22));
+        // and make and unmake keep them as playing the move does
+        let r#move = Move::Normal(square("e7"), square("f7"));
+        let played = captured.apply(r#move).unwrap();
+        let before = captured.to_fen();
+        let undo = captured.make(r#move);
+        assert_eq!(captured.to_fen(), played.to_fen());
+        captured.unmake(undo);
+        assert_eq!(captured.to_fen(), before);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let status = |fen| Game::from_fen(fen).expect("valid fen").status();
+        let insufficient = Some(Outcome::Draw(DrawReason::InsufficientMaterial));
+        for fen in [
+            "8/8/8/4k3/8/8/8/4K3 w - - 0 1",
+            "8/8/8/4k3/8/8/8/2N1K3 w - - 0 1",
+            // bishops all on light squares
+            "8/8/8/4k3/8/8/8/1b2KB2 w - - 0 1",
+        ] {
+            assert_eq!(status(fen), insufficient, "{fen}");
+        }
+        for fen in [
+            "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1",
+            "8/8/8/4k3/8/8/8/1NN1K3 w - - 0 1",
+            // bishops on both colors
+            "8/8/8/4k3/8/8/8/2B1KB2 w - - 0 1",
+            "8/8/8/4k3/8/8/8/2n1KB2 w - - 0 1",
+        ] {
+            assert_eq!(status(fen), None, "{fen}");
+        }
+    }
+
+    #[test]
+    fn for_each_move() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let mut moves = Vec::new();
+            game.for_each_move(|r#move| moves.push(r#move));
+            // the grouped moves, with four choices for each promotion
+            let grouped: usize = game
+                .moves()
+                .map(|(from, to)| {
+                    to.into_iter()
+                        .map(|to| if game.is_promotion(from, to) { 4 } else { 1 })
+                        .sum::<usize>()
+                })
+                .sum();
+            assert_eq!(moves.len(), grouped, "{fen}");
+            assert!(moves.iter().all(|&r#move| game.is_legal(r#move)), "{fen}");
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use crate::game::{
+    board::{position::Position, Board},
+    piece::{Piece, PieceColor, PieceType},
+};
+
+/// squares off the a-file, and so on, for keeping shifts from wrapping around the board
+const NOT_A_FILE: u64 = 0xfefe_fefe_fefe_fefe;
+const NOT_AB_FILES: u64 = 0xfcfc_fcfc_fcfc_fcfc;
+const NOT_H_FILE: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+const NOT_GH_FILES: u64 = 0x3f3f_3f3f_3f3f_3f3f;
+
+/// where each kind of piece stands, one bit per square indexed by `Position::0`, kept up to
+/// date move by move alongside the board so that attacks by knights and pawns can be found
+/// with a few shifts instead of a scan of the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bitboards([u64; 12]);
+
+impl Bitboards {
+    pub fn find(board: &Board) -> Self {
+        let mut bitboards = Self([0; 12]);
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (position, piece) in board.iter(color) {
+                bitboards.toggle(position, piece);
+            }
+        }
+        bitboards
+    }
+
+    pub fn pieces(&self, color: PieceColor, piece: PieceType) -> u64 {
+        self.0[index(Piece { color, piece })]
+    }
+
+    /// the squares `color`'s knights attack
+    pub fn attacks_from_knights(&self, color: PieceColor) -> u64 {
+        let knights = self.pieces(color, PieceType::Knight);
+        (knights << 17 & NOT_A_FILE)
+            | (knights << 15 & NOT_H_FILE)
+            | (knights << 10 & NOT_AB_FILES)
+            | (knights << 6 & NOT_GH_FILES)
+            | (knights >> 17 & NOT_H_FILE)
+            | (knights >> 15 & NOT_A_FILE)
+            | (knights >> 10 & NOT_GH_FILES)
+            | (knights >> 6 & NOT_AB_FILES)
+    }
+
+    /// the squares `color`'s pawns attack, diagonally forwards
+    pub fn attacks_from_pawns(&self, color: PieceColor) -> u64 {
+        let pawns = self.pieces(color, PieceType::Pawn);
+        match color {
+            PieceColor::White => (pawns << 9 & NOT_A_FILE) | (pawns << 7 & NOT_H_FILE),
+            PieceColor::Black => (pawns >> 7 & NOT_A_FILE) | (pawns >> 9 & NOT_H_FILE),
+        }
+    }
+
+    /// the bitboards once the piece at `from` on `board` moves to `to`, as `Board::make_move`
+    /// moves it
+    /// REQUIRES: there is a piece at `from`
+    pub fn after_move(self, board: &Board, from: Position, to: Position) -> Self {
+        let mut bitboards = self;
+        let piece = board.get(from).expect("Bitboards::after_move precondition");
+        if let Some((king_to, rook_from, rook_to)) = board.castling(from, to) {
+            let rook = board.get(rook_from).expect("castling with a rook");
+            bitboards.toggle(from, piece);
+            bitboards.toggle(king_to, piece);
+            bitboards.toggle(rook_from, rook);
+            bitboards.toggle(rook_to, rook);
+            return bitboards;
+        }
+        bitboards.capture(board, piece, from, to);
+        bitboards.toggle(from, piece);
+        bitboards.toggle(to, piece);
+        bitboards
+    }
+
+    /// the bitboards once the pawn at `from` on `board` moves to `to` and becomes a
+    /// `piece_type`
+    /// REQUIRES: there is a pawn at `from`
+    pub fn after_promotion(
+        self,
+        board: &Board,
+        from: Position,
+        to: Position,
+        piece_type: PieceType,
+    ) -> Self {
+        let mut bitboards = self;
+        let pawn = board
+            .get(from)
+            .expect("Bitboards::after_promotion precondition");
+        bitboards.capture(board, pawn, from, to);
+        bitboards.toggle(from, pawn);
+        let piece = Piece {
+            color: pawn.color,
+            piece: piece_type,
+        };
+        bitboards.toggle(to, piece);
+        bitboards
+    }
+
+    /// removes whatever `piece` captures moving from `from` to `to`, which for en passant is
+    /// not on `to`
+    fn capture(&mut self, board: &Board, piece: Piece, from: Position, to: Position) {
+        if let Some(captured) = board.get(to) {
+            self.toggle(to, captured);
+        } else if piece.piece == PieceType::Pawn && from.file() != to.file() {
+            let position = Position::new(from.rank(), to.file());
+            if let Some(captured) = board.get(position) {
+                self.toggle(position, captured);
+            }
+        }
+    }
+
+    fn toggle(&mut self, position: Position, piece: Piece) {
+        self.0[index(piece)] ^= 1 << position.0;
+    }
+}
+
+fn index(piece: Piece) -> usize {
+    6 * piece.color as usize + piece.piece as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::board::position::{Movement, ALL_POSITIONS};
+
+    #[test]
+    fn attacks() {
+        // every square, so that the masks are checked on every edge
+        for from in ALL_POSITIONS {
+            for color in [PieceColor::White, PieceColor::Black] {
+                let mut board = Board::new_empty();
+                board[from] = Some(Piece {
+                    color,
+                    piece: PieceType::Knight,
+                });
+                let knights = Bitboards::find(&board).attacks_from_knights(color);
+                for to in ALL_POSITIONS {
+                    let attacked = knights & 1 << to.0 != 0;
+                    assert_eq!(attacked, from.knight_apart(to), "{from} {to}");
+                }
+
+                if matches!(from.rank(), 0 | 7) {
+                    continue;
+                }
+                board[from] = Some(Piece {
+                    color,
+                    piece: PieceType::Pawn,
+                });
+                let pawns = Bitboards::find(&board).attacks_from_pawns(color);
+                let forward = from.pawn(color).expect("pawn not on last rank");
+                for to in ALL_POSITIONS {
+                    let attacked = pawns & 1 << to.0 != 0;
+                    let expected =
+                        to.rank() == forward.rank() && to.file().abs_diff(from.file()) == 1;
+                    assert_eq!(attacked, expected, "{color} {from} {to}");
+                }
+            }
+        }
+    }
+}
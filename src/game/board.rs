@@ -2,15 +2,36 @@ pub mod position;
 
 use std::{
     fmt::Debug,
+    hash::{Hash, Hasher},
     ops::{Index, IndexMut},
 };
 
 use crate::game::piece::{Piece, PieceColor, PieceType};
-use position::{Movement, Position};
+use position::Position;
 
-#[derive(Clone, Copy)]
+/// the pieces on each square, with no rules enforced: any placement can be built, e.g. from
+/// `new_empty` by indexing with a `Position`, and `Game::from_position` checks it is playable
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Board([Option<Piece>; 64]);
 
+impl Hash for Board {
+    /// XOR-folds `(square << 8) | piece` over the occupied squares
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let folded = self
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, piece)| piece.map(|piece| (i as u64) << 8 | encoding(piece)))
+            .fold(0, |folded, square| folded ^ square);
+        state.write_u64(folded);
+    }
+}
+
+/// a distinct nonzero number for each kind of piece
+fn encoding(piece: Piece) -> u64 {
+    1 + 6 * piece.color as u64 + piece.piece as u64
+}
+
 impl Index<Position> for Board {
     type Output = Option<Piece>;
 
@@ -27,8 +48,7 @@ impl IndexMut<Position> for Board {
 
 impl Board {
     pub fn new() -> Self {
-        let mut this = Self([None; 64]);
-        const PIECE_TYPES: [PieceType; 8] = [
+        Self::with_back_rank([
             PieceType::Rook,
             PieceType::Knight,
             PieceType::Bishop,
@@ -37,9 +57,15 @@ impl Board {
             PieceType::Bishop,
             PieceType::Knight,
             PieceType::Rook,
-        ];
+        ])
+    }
+
+    /// a starting position with the pieces behind the pawns in the order of `back_rank` from
+    /// the a-file, as in Chess960
+    pub fn with_back_rank(back_rank: [PieceType; 8]) -> Self {
+        let mut this = Self::new_empty();
         let color = PieceColor::Black;
-        for (file, piece) in PIECE_TYPES.into_iter().enumerate() {
+        for (file, piece) in back_rank.into_iter().enumerate() {
             this[Position::new(7, file as u8)] = Some(Piece { color, piece })
         }
         let piece = PieceType::Pawn;
@@ -50,12 +76,16 @@ impl Board {
         for file in 0..8 {
             this[Position::new(1, file)] = Some(Piece { color, piece })
         }
-        for (file, piece) in PIECE_TYPES.into_iter().enumerate() {
+        for (file, piece) in back_rank.into_iter().enumerate() {
             this[Position::new(0, file as u8)] = Some(Piece { color, piece })
         }
         this
     }
 
+    pub fn new_empty() -> Self {
+        Self([None; 64])
+    }
+
     pub fn get(&self, position: Position) -> Option<Piece> {
         self[position]
     }
@@ -68,15 +98,56 @@ impl Board {
         })
     }
 
+    pub fn iter_by_type(
+        &self,
+        piece: PieceType,
+        color: PieceColor,
+    ) -> impl Iterator<Item = Position> + Clone + '_ {
+        let target = Some(Piece { color, piece });
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, &p)| (p == target).then_some(Position(i as u8)))
+    }
+
+    /// yields every unordered pair of `color`'s pawns as `(file, rank)` coordinates,
+    /// for comparing pawns against each other in pawn structure analysis
+    pub fn iter_pawn_pairs(
+        &self,
+        color: PieceColor,
+    ) -> impl Iterator<Item = ((u8, u8), (u8, u8))> + '_ {
+        let pawns = self
+            .iter_by_type(PieceType::Pawn, color)
+            .map(|position| (position.file(), position.rank()));
+        pawns
+            .clone()
+            .enumerate()
+            .flat_map(move |(i, a)| pawns.clone().skip(i + 1).map(move |b| (a, b)))
+    }
+
     /// REQUIRES: there is a piece at `from`
     pub fn r#move(&self, from: Position, to: Position) -> Self {
         let mut new = *self;
-        let piece = new[from].take().expect("Board::r#move precondition");
-        let captured = new[to].replace(piece);
+        new.make_move(from, to);
+        new
+    }
+
+    /// `r#move`, in place
+    /// REQUIRES: there is a piece at `from`
+    pub fn make_move(&mut self, from: Position, to: Position) {
+        if let Some((king_to, rook_from, rook_to)) = self.castling(from, to) {
+            let king = self[from].take();
+            let rook = self[rook_from].take();
+            self[king_to] = king;
+            self[rook_to] = rook;
+            return;
+        }
+        let piece = self[from].take().expect("Board::r#move precondition");
+        let captured = self[to].replace(piece);
         // en passant
         if piece.piece == PieceType::Pawn && from.file() != to.file() && captured.is_none() {
             let captured_position = Position::new(from.rank(), to.file());
-            let captured = new[captured_position].take();
+            let captured = self[captured_position].take();
             debug_assert_eq!(
                 captured,
                 Some(Piece {
@@ -85,49 +156,64 @@ impl Board {
                 })
             );
         }
-        // castling
-        if piece.piece == PieceType::King && from.file().abs_diff(to.file()) == 2 {
-            let (rook_from, rook_to) = if to.file() < from.file() {
-                // queenside
-                (to.left().left(), to.right())
-            } else {
-                // kingside
-                (to.right(), to.left())
-            };
-            let (rook_from, rook_to) = (
-                rook_from.expect("Board::move castling precondition"),
-                rook_to.expect("Board::move castling precondition"),
-            );
-            let rook = new[rook_from].take();
-            debug_assert_eq!(
-                rook.expect("Board::move castling precondition"),
-                Piece {
-                    color: piece.color,
-                    piece: PieceType::Rook
-                }
-            );
-            new[rook_to] = rook;
+    }
+
+    /// if moving the piece at `from` to `to` would castle, where the king and rook end up and
+    /// where the rook starts, as `(king_to, rook_from, rook_to)`. Castling is written as the
+    /// king moving two squares towards the rook or, in Chess960, onto it.
+    pub fn castling(&self, from: Position, to: Position) -> Option<(Position, Position, Position)> {
+        let king = self
+            .get(from)
+            .filter(|piece| piece.piece == PieceType::King)?;
+        let rook = Some(Piece {
+            color: king.color,
+            piece: PieceType::Rook,
+        });
+        let rook_from = if self.get(to) == rook {
+            to
+        } else if from.rank() == to.rank() && from.file().abs_diff(to.file()) == 2 {
+            let file = if to.file() > from.file() { 7 } else { 0 };
+            Position::new(from.rank(), file)
+        } else {
+            return None;
+        };
+        if self.get(rook_from) != rook {
+            return None;
         }
-        new
+        let (king_to, rook_to) = if rook_from.file() > from.file() {
+            (6, 5)
+        } else {
+            (2, 3)
+        };
+        Some((
+            Position::new(from.rank(), king_to),
+            rook_from,
+            Position::new(from.rank(), rook_to),
+        ))
     }
 
     pub fn promote(&self, from: Position, to: Position, piece_type: PieceType) -> Self {
-        let mut new = self.clone();
-        let piece = new[from].take().expect("Board::promote precondition");
+        let mut new = *self;
+        new.make_promotion(from, to, piece_type);
+        new
+    }
+
+    /// `promote`, in place
+    pub fn make_promotion(&mut self, from: Position, to: Position, piece_type: PieceType) {
+        let piece = self[from].take().expect("Board::promote precondition");
         debug_assert_eq!(piece.piece, PieceType::Pawn);
         let piece = Piece {
             color: piece.color,
             piece: piece_type,
         };
-        new[to] = Some(piece);
-        new
+        self[to] = Some(piece);
     }
 
     pub fn position_of(&self, piece: Piece) -> Option<Position> {
         self.0
             .iter()
             .enumerate()
-            .find_map(|(i, &p)| p.is_some_and(|p| p == piece).then(|| Position(i as u8)))
+            .find_map(|(i, &p)| p.is_some_and(|p| p == piece).then_some(Position(i as u8)))
     }
 
     pub fn is_vacant(&self, position: Position) -> bool {
@@ -135,31 +221,31 @@ impl Board {
     }
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// a diagram with white at the bottom, e.g. for the starting position
+/// ```text
+/// 8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+/// 7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+/// 6 · · · · · · · ·
+/// ⋮
+/// 1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+///   a b c d e f g h
+/// ```
 impl Debug for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use colored::{Color, Colorize};
         for rank in (0..8).rev() {
-            write!(f, "{} ", rank + 1)?;
+            write!(f, "{}", rank + 1)?;
             for file in 0..8 {
-                let fg = match self[Position::new(rank, file)] {
-                    Some(piece) => {
-                        let color = match piece.color {
-                            PieceColor::White => Color::White,
-                            PieceColor::Black => Color::Black,
-                        };
-                        format!(" {}", piece.piece.render()).color(color)
-                    }
-                    None => "  ".into(),
-                };
-                let bg = if (rank + file) % 2 == 0 {
-                    Color::Green
-                } else {
-                    Color::BrightGreen
-                };
-                write!(f, "{}", fg.on_color(bg))?;
+                let symbol = self[Position::new(rank, file)].map_or('·', Piece::to_unicode);
+                write!(f, " {symbol}")?;
             }
             writeln!(f)?;
         }
-        write!(f, "   a b c d e f g h")
+        write!(f, "  a b c d e f g h")
     }
 }
@@ -1,13 +1,88 @@
-use std::fmt::Display;
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
 use ratatui::prelude::*;
 
-use crate::game::piece::PieceColor;
+use crate::{game::piece::PieceColor, tui::Theme};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-pub struct Position(pub(super) u8);
+pub struct Position(pub(crate) u8);
+
+/// every square, in rank-major order from a1, b1, … to h8, so that `ALL_POSITIONS[i].0 == i`
+pub const ALL_POSITIONS: [Position; 64] = {
+    let mut all = [Position(0); 64];
+    let mut i = 0;
+    while i < 64 {
+        all[i] = Position(i as u8);
+        i += 1;
+    }
+    all
+};
+
+/// `targets[from]` has bit `to` set when one of `steps`, as `(ranks, files)`, leads from
+/// square `from` to square `to` without leaving the board
+const fn targets(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut targets = [0; 64];
+    let mut from = 0;
+    while from < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            let (d_rank, d_file) = steps[i];
+            let rank = (from >> 3) as i8 + d_rank;
+            let file = (from & 0b111) as i8 + d_file;
+            if 0 <= rank && rank < 8 && 0 <= file && file < 8 {
+                targets[from] |= 1 << (rank << 3 | file);
+            }
+            i += 1;
+        }
+        from += 1;
+    }
+    targets
+}
+
+/// the squares a knight on each square attacks, as bitboards of square numbers
+static KNIGHT_ATTACKS: [u64; 64] = targets(&[
+    (2, -1),
+    (2, 1),
+    (1, -2),
+    (1, 2),
+    (-1, -2),
+    (-1, 2),
+    (-2, -1),
+    (-2, 1),
+]);
+
+/// the squares a king on each square attacks, as bitboards of square numbers
+static KING_ATTACKS: [u64; 64] = targets(&[
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+]);
+
+/// the squares in a set of square numbers, from a1 up
+fn squares(mut set: u64) -> impl Iterator<Item = Position> {
+    std::iter::from_fn(move || {
+        (set != 0).then(|| {
+            let square = set.trailing_zeros() as u8;
+            set &= set - 1;
+            Position(square)
+        })
+    })
+}
 
 impl Position {
+    /// every square, in the order of `ALL_POSITIONS`
+    pub fn all() -> impl Iterator<Item = Position> {
+        ALL_POSITIONS.into_iter()
+    }
+
     pub fn new(rank: u8, file: u8) -> Self {
         debug_assert!(rank < 8);
         debug_assert!(file < 8);
@@ -22,13 +97,47 @@ impl Position {
         self.0 & 0b111
     }
 
-    pub fn square(self) -> Line<'static> {
-        let bg = if (self.rank() + self.file()) % 2 == 0 {
-            Color::DarkGray
-        } else {
-            Color::Gray
+    /// the same square as seen from the other side of the board, e.g. e2 becomes e7
+    pub fn flip_rank(self) -> Self {
+        Self::new(7 - self.rank(), self.file())
+    }
+
+    /// number of king steps to the nearest of the four central squares, from 0 to 6
+    pub fn center_distance(self) -> u8 {
+        let from_center = |coordinate: u8| {
+            3u8.saturating_sub(coordinate)
+                .max(coordinate.saturating_sub(4))
         };
-        Line::default().bg(bg)
+        from_center(self.rank()) + from_center(self.file())
+    }
+
+    /// whether `other` is one king step away
+    pub fn adjacent(self, other: Position) -> bool {
+        KING_ATTACKS[self.0 as usize] >> other.0 & 1 == 1
+    }
+
+    /// whether `other` is a knight's move away
+    pub fn knight_apart(self, other: Position) -> bool {
+        KNIGHT_ATTACKS[self.0 as usize] >> other.0 & 1 == 1
+    }
+
+    /// the squares a king here steps to, ignoring what stands on them
+    pub fn king_targets(self) -> impl Iterator<Item = Position> {
+        squares(KING_ATTACKS[self.0 as usize])
+    }
+
+    /// the squares a knight here moves to, ignoring what stands on them
+    pub fn knight_targets(self) -> impl Iterator<Item = Position> {
+        squares(KNIGHT_ATTACKS[self.0 as usize])
+    }
+
+    /// whether this is one of the light squares, such as h1
+    pub fn is_light(self) -> bool {
+        !(self.rank() + self.file()).is_multiple_of(2)
+    }
+
+    pub fn square(self, theme: &Theme) -> Line<'static> {
+        Line::default().bg(theme.square(self))
     }
 }
 
@@ -38,6 +147,50 @@ impl Display for Position {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParsePositionError(String);
+
+impl Display for ParsePositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid square {:?}: expected a file a-h followed by a rank 1-8, such as \"e4\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParsePositionError {}
+
+impl FromStr for Position {
+    type Err = ParsePositionError;
+
+    /// parses algebraic notation such as `e4`, case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let &[file, rank] = s.as_bytes() else {
+            return Err(ParsePositionError(s.to_string()));
+        };
+        let (file @ b'a'..=b'h', rank @ b'1'..=b'8') = (file.to_ascii_lowercase(), rank) else {
+            return Err(ParsePositionError(s.to_string()));
+        };
+        Ok(Self::new(rank - b'1', file - b'a'))
+    }
+}
+
+impl TryFrom<&str> for Position {
+    type Error = ParsePositionError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Debug for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
 pub trait Movement {
     fn up(self) -> Option<Position>;
     fn down(self) -> Option<Position>;
@@ -92,3 +245,55 @@ impl Movement for Option<Position> {
         self.and_then(|position| position.pawn(color))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Movement, Position, ALL_POSITIONS, KING_ATTACKS, KNIGHT_ATTACKS};
+
+    #[test]
+    fn move_tables() {
+        // nothing is cut off by the edge of the board in the middle
+        let d4 = Position::new(3, 3).0 as usize;
+        assert_eq!(KNIGHT_ATTACKS[d4].count_ones(), 8);
+        assert_eq!(KING_ATTACKS[d4].count_ones(), 8);
+        // whereas a corner leaves only two knight moves and three king moves
+        assert_eq!(KNIGHT_ATTACKS[0].count_ones(), 2);
+        assert_eq!(KING_ATTACKS[0].count_ones(), 3);
+        for from in ALL_POSITIONS {
+            let mut knight: Vec<Position> = [
+                from.up().up().left(),
+                from.up().up().right(),
+                from.left().left().up(),
+                from.left().left().down(),
+                from.down().down().left(),
+                from.down().down().right(),
+                from.right().right().up(),
+                from.right().right().down(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            knight.sort();
+            assert_eq!(from.knight_targets().collect::<Vec<_>>(), knight, "{from}");
+            let mut king: Vec<Position> = [
+                from.up(),
+                from.up().right(),
+                from.right(),
+                from.down().right(),
+                from.down(),
+                from.down().left(),
+                from.left(),
+                from.up().left(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            king.sort();
+            assert_eq!(from.king_targets().collect::<Vec<_>>(), king, "{from}");
+            for to in ALL_POSITIONS {
+                assert_eq!(from.knight_apart(to), knight.contains(&to), "{from} {to}");
+                assert_eq!(from.adjacent(to), king.contains(&to), "{from} {to}");
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::ops::{Index, IndexMut};
 use crate::game::piece::PieceColor;
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CastlingInfo {
     KingHasNotMoved {
         queenside_rook_has_not_moved: bool,
@@ -11,7 +12,18 @@ pub enum CastlingInfo {
     KingHasMoved,
 }
 
-// TODO: make updates return Self instead of mutate once I switch to persistent data structures
+/// compares the rights rather than the representation, since a king that has not moved but
+/// has lost both rooks can no more castle than one that has moved
+impl PartialEq for CastlingInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.can_castle_queenside() == other.can_castle_queenside()
+            && self.can_castle_kingside() == other.can_castle_kingside()
+    }
+}
+
+impl Eq for CastlingInfo {}
+
+// updates mutate rather than return Self, since the search makes and unmakes moves in place
 impl CastlingInfo {
     fn new() -> Self {
         Self::KingHasNotMoved {
@@ -20,6 +32,17 @@ impl CastlingInfo {
         }
     }
 
+    pub fn from_rights(queenside: bool, kingside: bool) -> Self {
+        if queenside || kingside {
+            Self::KingHasNotMoved {
+                queenside_rook_has_not_moved: queenside,
+                kingside_rook_has_not_moved: kingside,
+            }
+        } else {
+            Self::KingHasMoved
+        }
+    }
+
     pub fn move_king(&mut self) {
         *self = Self::KingHasMoved
     }
@@ -65,17 +88,69 @@ impl CastlingInfo {
     }
 }
 
-#[derive(Clone, Copy)]
+/// the files the king and rooks that may castle start on, the same for both colors
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastlingFiles {
+    pub king: u8,
+    pub queenside_rook: u8,
+    pub kingside_rook: u8,
+}
+
+impl CastlingFiles {
+    pub const STANDARD: Self = Self {
+        king: 4,
+        queenside_rook: 0,
+        kingside_rook: 7,
+    };
+
+    pub fn rook(self, kingside: bool) -> u8 {
+        if kingside {
+            self.kingside_rook
+        } else {
+            self.queenside_rook
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Castling {
     white: CastlingInfo,
     black: CastlingInfo,
+    files: CastlingFiles,
 }
 
 impl Castling {
     pub fn new() -> Self {
+        Self::with_files(CastlingFiles::STANDARD)
+    }
+
+    /// full rights for a king and rooks starting on `files`, as in Chess960
+    pub fn with_files(files: CastlingFiles) -> Self {
         let white = CastlingInfo::new();
         let black = CastlingInfo::new();
-        Self { white, black }
+        Self {
+            white,
+            black,
+            files,
+        }
+    }
+
+    pub fn files(&self) -> CastlingFiles {
+        self.files
+    }
+
+    /// whether the king or rooks start somewhere other than in standard chess, in which case
+    /// castling is written as the king moving onto the rook rather than two squares
+    pub fn is_chess960(&self) -> bool {
+        self.files != CastlingFiles::STANDARD
+    }
+}
+
+impl Default for Castling {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
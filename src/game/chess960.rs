@@ -0,0 +1,136 @@
+use std::sync::OnceLock;
+
+use crate::game::{
+    bitboards::Bitboards,
+    board::Board,
+    castling::{Castling, CastlingFiles},
+    kings::Kings,
+    piece::{PieceColor, PieceType},
+    Game,
+};
+
+/// which two of the five squares left after the bishops and queen the knights take, by the
+/// knights' digit of the starting position's number
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+impl Game {
+    /// Chess960 starting position number `n`, in the standard numbering in which 518 is the
+    /// usual starting position.
+    /// REQUIRES: n < 960
+    pub fn chess960(n: u16) -> Self {
+        assert!(n < 960, "Game::chess960 precondition");
+        let mut back_rank = [None; 8];
+        let mut n = n as usize;
+        let mut digit = |base: usize| {
+            let digit = n % base;
+            n /= base;
+            digit
+        };
+        // one bishop on a light square and the other on a dark square
+        back_rank[2 * digit(4) + 1] = Some(PieceType::Bishop);
+        back_rank[2 * digit(4)] = Some(PieceType::Bishop);
+        let queen = digit(6);
+        let (first_knight, second_knight) = KNIGHTS[digit(10)];
+        // the rest fill the empty squares in turn, with the king between the rooks
+        let mut empty: Vec<usize> = (0..8).filter(|&file| back_rank[file].is_none()).collect();
+        back_rank[empty.remove(queen)] = Some(PieceType::Queen);
+        back_rank[empty[first_knight]] = Some(PieceType::Knight);
+        back_rank[empty[second_knight]] = Some(PieceType::Knight);
+        empty.retain(|&file| back_rank[file].is_none());
+        let [queenside_rook, king, kingside_rook] = empty[..] else {
+            unreachable!("three squares are left for the king and rooks")
+        };
+        back_rank[queenside_rook] = Some(PieceType::Rook);
+        back_rank[king] = Some(PieceType::King);
+        back_rank[kingside_rook] = Some(PieceType::Rook);
+        let board = Board::with_back_rank(back_rank.map(|piece| piece.expect("every file filled")));
+        let mut game = Self {
+            turn: PieceColor::White,
+            board,
+            just_advanced_two: None,
+            castling: Castling::with_files(CastlingFiles {
+                king: king as u8,
+                queenside_rook: queenside_rook as u8,
+                kingside_rook: kingside_rook as u8,
+            }),
+            kings: Kings::find(&board),
+            bitboards: Bitboards::find(&board),
+            attack_table: OnceLock::new(),
+            hash: 0,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        };
+        game.hash = game.zobrist();
+        game
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::{square, Game, Move};
+
+    #[test]
+    fn starting_positions() {
+        let fen = |n| Game::chess960(n).to_fen();
+        assert_eq!(
+            fen(0),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(fen(518), Game::new().to_fen());
+        assert!(Game::chess960(518) == Game::new());
+        assert_eq!(
+            fen(959),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"
+        );
+        for n in 0..960 {
+            assert_eq!(
+                Game::from_fen(&fen(n)).map(|game| game.to_fen()),
+                Ok(fen(n))
+            );
+        }
+    }
+
+    #[test]
+    fn castling() {
+        let uci = |game: &Game, s| Move::from_uci_str(s, game).unwrap();
+        // the king on b1 castles queenside by moving onto the rook on a1, ending on c1 with the
+        // rook beside it on d1
+        let game = Game::from_fen("rk5r/8/8/8/8/8/8/RK5R w KQkq - 0 1").expect("valid fen");
+        let castled = game.apply(uci(&game, "b1a1")).unwrap();
+        assert_eq!(castled.to_fen(), "rk5r/8/8/8/8/8/8/2KR3R b kq - 1 1");
+        assert_eq!(game.san(Move::Normal(square("b1"), square("a1"))), "O-O-O");
+        assert_eq!(game.san(Move::Normal(square("b1"), square("h1"))), "O-O");
+        assert_eq!(
+            game.hash_after_move(square("b1"), square("a1")),
+            castled.hash()
+        );
+        // a rook on g1 shields g1 itself, so castling kingside would leave the king in check
+        let game = Game::from_fen("6rk/8/8/8/8/8/8/5KRr w K - 0 1").expect("valid fen");
+        assert!(Move::from_uci_str("f1g1", &game).is_err());
+        // and in standard chess the king may castle onto the rook too
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").expect("valid fen");
+        assert_eq!(uci(&game, "e1h1"), Move::Normal(square("e1"), square("g1")));
+    }
+
+    #[test]
+    fn perft() {
+        assert_eq!(Game::chess960(0).perft(3), 9_006);
+        assert_eq!(Game::chess960(959).perft(3), 9_006);
+        // castling both ways for both sides, written in Shredder-FEN
+        let game =
+            Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+                .expect("valid fen");
+        assert_eq!(game.perft(3), 12_189);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt::Display;
+
+use crate::game::{
+    board::{position::Position, Board},
+    castling::{Castling, CastlingFiles, CastlingInfo},
+    piece::{Piece, PieceColor, PieceType},
+    setup::PositionError,
+    Game,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    MissingField(&'static str),
+    Placement(String),
+    Turn(String),
+    Castling(String),
+    EnPassant(String),
+    Clock(String),
+    /// the fields parse, but do not describe a position that can arise in a game
+    Position(PositionError),
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {field} field"),
+            FenError::Placement(placement) => write!(f, "invalid piece placement {placement:?}"),
+            FenError::Turn(turn) => write!(f, "invalid active color {turn:?}"),
+            FenError::Castling(castling) => write!(f, "invalid castling rights {castling:?}"),
+            FenError::EnPassant(en_passant) => {
+                write!(f, "invalid en passant square {en_passant:?}")
+            }
+            FenError::Clock(clock) => write!(f, "invalid move clock {clock:?}"),
+            FenError::Position(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+pub(super) fn parse_placement(placement: &str) -> Result<Board, FenError> {
+    let error = || FenError::Placement(placement.to_string());
+    let mut board = Board::new_empty();
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(error());
+    }
+    for (rank, row) in (0..8).rev().zip(ranks) {
+        let mut file = 0;
+        for c in row.chars() {
+            if let Some(skip) = c.to_digit(10) {
+                file += skip as u8;
+            } else {
+                let piece = Piece::from_fen_char(c).ok_or_else(error)?;
+                if file >= 8 {
+                    return Err(error());
+                }
+                board[Position::new(rank, file)] = Some(piece);
+                file += 1;
+            }
+        }
+        if file != 8 {
+            return Err(error());
+        }
+    }
+    Ok(board)
+}
+
+pub(super) fn format_placement(board: &Board) -> String {
+    let mut placement = String::new();
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for file in 0..8 {
+            match board.get(Position::new(rank, file)) {
+                Some(piece) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(piece.to_fen_char());
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            placement.push('/');
+        }
+    }
+    placement
+}
+
+/// Reads `KQkq` as the rook furthest from the king on that side, as X-FEN does, so that
+/// Chess960 positions need no special notation in most cases. A rook's file may also be given
+/// instead, as Shredder-FEN does, e.g. `HAha`.
+fn parse_castling(castling: &str, board: &Board) -> Result<Castling, FenError> {
+    let error = || FenError::Castling(castling.to_string());
+    // the king's, queenside rook's and kingside rook's files, once any right has implied them
+    let mut files: [Option<u8>; 3] = [None; 3];
+    let mut rights = [[false; 2]; 2];
+    for c in castling.chars().filter(|_| castling != "-") {
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let rank = color.piece_starting_rank();
+        let on_rank = |piece| {
+            board
+                .iter_by_type(piece, color)
+                .filter(move |position| position.rank() == rank)
+                .map(|position| position.file())
+        };
+        let king = on_rank(PieceType::King).next().ok_or_else(error)?;
+        let mut rooks = on_rank(PieceType::Rook);
+        let rook = match c.to_ascii_lowercase() {
+            'k' => rooks.filter(|&file| file > king).max(),
+            'q' => rooks.find(|&file| file < king),
+            file @ 'a'..='h' => rooks.find(|&rook| rook == file as u8 - b'a'),
+            _ => None,
+        }
+        .ok_or_else(error)?;
+        let kingside = rook > king;
+        for (slot, file) in [(0, king), (1 + kingside as usize, rook)] {
+            if *files[slot].get_or_insert(file) != file {
+                return Err(error());
+            }
+        }
+        rights[color as usize][kingside as usize] = true;
+    }
+    let standard = CastlingFiles::STANDARD;
+    let mut castling = Castling::with_files(CastlingFiles {
+        king: files[0].unwrap_or(standard.king),
+        queenside_rook: files[1].unwrap_or(standard.queenside_rook),
+        kingside_rook: files[2].unwrap_or(standard.kingside_rook),
+    });
+    for color in [PieceColor::White, PieceColor::Black] {
+        let [queenside, kingside] = rights[color as usize];
+        castling[color] = CastlingInfo::from_rights(queenside, kingside);
+    }
+    Ok(castling)
+}
+
+/// `KQkq` where the rook is the furthest from the king on its side, which is always so in
+/// standard chess, or else the rook's file
+pub(super) fn format_castling(castling: &Castling, board: &Board) -> String {
+    let files = castling.files();
+    let mut rights = String::new();
+    for color in [PieceColor::White, PieceColor::Black] {
+        let rank = color.piece_starting_rank();
+        for (kingside, can_castle, letter) in [
+            (true, castling[color].can_castle_kingside(), 'K'),
+            (false, castling[color].can_castle_queenside(), 'Q'),
+        ] {
+            if !can_castle {
+                continue;
+            }
+            let rook = files.rook(kingside);
+            let outermost = board
+                .iter_by_type(PieceType::Rook, color)
+                .filter(|position| position.rank() == rank)
+                .all(|position| {
+                    if kingside {
+                        position.file() <= rook
+                    } else {
+                        position.file() >= rook
+                    }
+                });
+            let letter = if outermost {
+                letter
+            } else {
+                (b'A' + rook) as char
+            };
+            rights.push(match color {
+                PieceColor::White => letter,
+                PieceColor::Black => letter.to_ascii_lowercase(),
+            });
+        }
+    }
+    if rights.is_empty() {
+        rights.push('-');
+    }
+    rights
+}
+
+impl Game {
+    /// the position in Forsyth–Edwards Notation
+    pub fn to_fen(&self) -> String {
+        let turn = match self.turn {
+            PieceColor::White => 'w',
+            PieceColor::Black => 'b',
+        };
+        let en_passant = self
+            .en_passant_square()
+            .map_or("-".to_string(), |target| target.to_string());
+        format!(
+            "{} {turn} {} {en_passant} {} {}",
+            format_placement(&self.board),
+            format_castling(&self.castling, &self.board),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// parses a position in Forsyth–Edwards Notation.
+    /// The halfmove clock and fullmove number are optional, and default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::MissingField("placement"))?;
+        let turn = fields
+            .next()
+            .ok_or(FenError::MissingField("active color"))?;
+        let castling = fields.next().ok_or(FenError::MissingField("castling"))?;
+        let en_passant = fields.next().ok_or(FenError::MissingField("en passant"))?;
+        let mut clock = |default| {
+            fields.next().map_or(Ok(default), |clock| {
+                clock
+                    .parse::<u32>()
+                    .map_err(|_| FenError::Clock(clock.to_string()))
+            })
+        };
+        let halfmove_clock = clock(0)?;
+        let fullmove_number = clock(1)?;
+
+        let board = parse_placement(placement)?;
+
+        let turn = match turn {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::Turn(turn.to_string())),
+        };
+
+        let castling = parse_castling(castling, &board)?;
+
+        let en_passant = match en_passant {
+            "-" => None,
+            square => Some(
+                square
+                    .parse()
+                    .map_err(|_| FenError::EnPassant(square.to_string()))?,
+            ),
+        };
+
+        Self::from_position(
+            board,
+            turn,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        )
+        .map_err(FenError::Position)
+    }
+
+    /// whether `fen` is a well-formed, legal position, without keeping the game.
+    /// Beyond the syntax, the position must meet the invariants of `Game::from_position`.
+    pub fn validate_fen(fen: &str) -> Result<(), FenError> {
+        Self::from_fen(fen).map(drop)
+    }
+}
@@ -0,0 +1,66 @@
+use std::ops::{Index, IndexMut};
+
+use crate::game::{
+    board::{position::Position, Board},
+    piece::{Piece, PieceColor, PieceType},
+};
+
+/// where each side's king stands, kept up to date move by move so that finding it does not
+/// take a scan of the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Kings {
+    white: Position,
+    black: Position,
+}
+
+impl Kings {
+    /// REQUIRES: each side has a king on `board`
+    pub fn find(board: &Board) -> Self {
+        let find = |color| {
+            board
+                .position_of(Piece {
+                    color,
+                    piece: PieceType::King,
+                })
+                .expect("king always exists")
+        };
+        Self {
+            white: find(PieceColor::White),
+            black: find(PieceColor::Black),
+        }
+    }
+
+    /// where the kings stand once the piece at `from` on `board` moves to `to`, which may be
+    /// castling
+    /// REQUIRES: there is a piece at `from`
+    pub fn after_move(self, board: &Board, from: Position, to: Position) -> Self {
+        let piece = board.get(from).expect("Kings::after_move precondition");
+        let mut kings = self;
+        if piece.piece == PieceType::King {
+            kings[piece.color] = board
+                .castling(from, to)
+                .map_or(to, |(king_to, _, _)| king_to);
+        }
+        kings
+    }
+}
+
+impl Index<PieceColor> for Kings {
+    type Output = Position;
+
+    fn index(&self, color: PieceColor) -> &Self::Output {
+        match color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        }
+    }
+}
+
+impl IndexMut<PieceColor> for Kings {
+    fn index_mut(&mut self, color: PieceColor) -> &mut Self::Output {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt::Display;
+
+use std::sync::OnceLock;
+
+use crate::game::{
+    bitboards::Bitboards, castling::Castling, forfeit_captured_rook, kings::Kings, Game, Piece,
+    PieceType, Position, PROMOTIONS,
+};
+
+/// a move in a game: a piece from one square to another, and what a pawn promotes to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Move {
+    Normal(Position, Position),
+    Promote(Position, Position, PieceType),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseMoveError {
+    /// not in coordinate notation
+    Malformed(String),
+    /// well-formed, but not a legal move in the game
+    Illegal(Move),
+}
+
+impl Display for ParseMoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseMoveError::Malformed(s) => write!(
+                f,
+                "invalid move {s:?}: expected two squares and an optional promotion, \
+                 such as \"e7e8q\""
+            ),
+            ParseMoveError::Illegal(r#move) => write!(f, "{move} is not a legal move"),
+        }
+    }
+}
+
+impl std::error::Error for ParseMoveError {}
+
+/// why a move cannot be played
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MoveError {
+    /// the player to move has no piece on the square moved from
+    NoPieceAtSource(Position),
+    /// the piece cannot move there, or a pawn cannot promote to that piece
+    IllegalMove(Move),
+    /// names a piece to promote to, but is not a pawn reaching the last rank
+    NotAPromotion(Move),
+    /// a pawn reaching the last rank without naming a piece to promote to
+    MissingPromotion(Move),
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoPieceAtSource(position) => {
+                write!(f, "the player to move has no piece on {position}")
+            }
+            MoveError::IllegalMove(r#move) => write!(f, "{move} is not a legal move"),
+            MoveError::NotAPromotion(r#move) => write!(f, "{move} is not a promotion"),
+            MoveError::MissingPromotion(r#move) => {
+                write!(f, "{move} must name a piece to promote to")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// what `Game::make` changed, for `Game::unmake` to put back
+#[derive(Clone, Copy)]
+pub struct Undo {
+    r#move: Move,
+    /// the piece captured and where it stood, which for en passant is not the destination
+    captured: Option<(Position, Piece)>,
+    /// where the king and rook ended up and where the rook started, if the move castled
+    castled: Option<(Position, Position, Position)>,
+    just_advanced_two: Option<Position>,
+    castling: Castling,
+    kings: Kings,
+    bitboards: Bitboards,
+    hash: u64,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+/// coordinate notation, as UCI uses (e.g. `e2e4`, `e7e8q`)
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Move::Normal(from, to) => write!(f, "{from}{to}"),
+            Move::Promote(from, to, piece_type) => {
+                write!(f, "{from}{to}{}", piece_type.to_char().to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+impl Move {
+    pub fn new(from: Position, to: Position, promotion: Option<PieceType>) -> Self {
+        match promotion {
+            None => Move::Normal(from, to),
+            Some(piece_type) => Move::Promote(from, to, piece_type),
+        }
+    }
+
+    pub fn from(self) -> Position {
+        let (Move::Normal(from, _) | Move::Promote(from, _, _)) = self;
+        from
+    }
+
+    pub fn to(self) -> Position {
+        let (Move::Normal(_, to) | Move::Promote(_, to, _)) = self;
+        to
+    }
+
+    pub fn promotion(self) -> Option<PieceType> {
+        match self {
+            Move::Normal(..) => None,
+            Move::Promote(_, _, piece_type) => Some(piece_type),
+        }
+    }
+
+    /// parses a move in UCI coordinate notation (e.g. `e2e4`, `e7e8q`) that must be legal in
+    /// `game`
+    pub fn from_uci_str(s: &str, game: &Game) -> Result<Self, ParseMoveError> {
+        let parse = || {
+            let from = s.get(0..2)?.parse().ok()?;
+            let to = s.get(2..4)?.parse().ok()?;
+            let mut promotion = s.get(4..)?.chars();
+            match (promotion.next(), promotion.next()) {
+                (None, _) => Some(Move::Normal(from, to)),
+                (Some(c @ 'a'..='z'), None) => {
+                    Some(Move::Promote(from, to, PieceType::from_char(c)?))
+                }
+                _ => None,
+            }
+        };
+        let mut r#move = parse().ok_or_else(|| ParseMoveError::Malformed(s.to_string()))?;
+        // UCI writes castling in Chess960 as the king moving onto its own rook, which some
+        // interfaces do for standard chess too
+        if let Move::Normal(from, to) = r#move {
+            if let Some((king_to, rook_from, _)) = game.board.castling(from, to) {
+                if rook_from == to && !game.castling.is_chess960() {
+                    r#move = Move::Normal(from, king_to);
+                }
+            }
+        }
+        if game.is_legal(r#move) {
+            Ok(r#move)
+        } else {
+            Err(ParseMoveError::Illegal(r#move))
+        }
+    }
+}
+
+impl Game {
+    /// every move that can be played, with each promotion choice a move of its own, in the
+    /// order of `moves()`
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut legal_moves = Vec::new();
+        self.for_each_move(|r#move| legal_moves.push(r#move));
+        legal_moves
+    }
+
+    /// `legal_moves().len()`, counted without collecting them
+    pub fn legal_move_count(&self) -> usize {
+        let mut count = 0;
+        self.for_each_move(|_| count += 1);
+        count
+    }
+
+    /// calls `f` with each of `legal_moves` in turn, without allocating, for the search's hot
+    /// path
+    pub fn for_each_move(&self, mut f: impl FnMut(Move)) {
+        for (from, piece) in self.board.iter(self.turn) {
+            self.for_each_pseudo_move_of(from, piece, |to| {
+                if !self.does_not_cause_check(from, to) {
+                    return;
+                }
+                if self.is_promotion(from, to) {
+                    for piece_type in PROMOTIONS {
+                        f(Move::Promote(from, to, piece_type));
+                    }
+                } else {
+                    f(Move::Normal(from, to));
+                }
+            });
+        }
+    }
+
+    /// whether `r#move` can be played, including that it promotes exactly when it must
+    pub fn is_legal(&self, r#move: Move) -> bool {
+        self.check_legal(r#move).is_ok()
+    }
+
+    fn check_legal(&self, r#move: Move) -> Result<(), MoveError> {
+        let (from, to) = (r#move.from(), r#move.to());
+        let moves = self
+            .legal_moves_from(from)
+            .ok_or(MoveError::NoPieceAtSource(from))?;
+        if !moves.contains(&to) {
+            return Err(MoveError::IllegalMove(r#move));
+        }
+        match (r#move.promotion(), self.is_promotion(from, to)) {
+            (None, true) => Err(MoveError::MissingPromotion(r#move)),
+            (Some(_), false) => Err(MoveError::NotAPromotion(r#move)),
+            (Some(piece_type), true) if !PROMOTIONS.contains(&piece_type) => {
+                Err(MoveError::IllegalMove(r#move))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// plays `r#move`, or says why it cannot be played, for moves from outside the crate such
+    /// as a UCI interface's or a PGN file's
+    pub fn try_apply(&self, r#move: Move) -> Result<Self, MoveError> {
+        self.check_legal(r#move)?;
+        Ok(self.apply_move_unchecked(r#move))
+    }
+
+    /// plays `r#move`, or returns None if it is illegal
+    pub fn apply(&self, r#move: Move) -> Option<Self> {
+        self.try_apply(r#move).ok()
+    }
+
+    /// plays `r#move` in place, returning what `unmake` needs to take it back. Unlike
+    /// `apply_move_unchecked`, this copies nothing, which is what the search wants.
+    /// REQUIRES: `r#move` is legal
+    pub fn make(&mut self, r#move: Move) -> Undo {
+        let (from, to) = (r#move.from(), r#move.to());
+        let piece = self.board[from].expect("Game::make precondition");
+        let castled = self.board.castling(from, to);
+        let captured = if castled.is_some() {
+            None
+        } else if let Some(captured) = self.board[to] {
+            Some((to, captured))
+        } else if piece.piece == PieceType::Pawn && from.file() != to.file() {
+            let pawn = Position::new(from.rank(), to.file());
+            self.board[pawn].map(|captured| (pawn, captured))
+        } else {
+            None
+        };
+        let undo = Undo {
+            r#move,
+            captured,
+            castled,
+            just_advanced_two: self.just_advanced_two,
+            castling: self.castling,
+            kings: self.kings,
+            bitboards: self.bitboards,
+            hash: self.hash,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+        self.hash = self.hash_after(r#move);
+        (self.halfmove_clock, self.fullmove_number) =
+            self.clocks_after(piece.piece == PieceType::Pawn || captured.is_some());
+        match r#move {
+            Move::Normal(from, to) => {
+                self.just_advanced_two = (piece.piece == PieceType::Pawn
+                    && from.rank().abs_diff(to.rank()) == 2)
+                    .then_some(to);
+                self.castling = self.castling_after_move(piece, from, to);
+                self.kings = self.kings.after_move(&self.board, from, to);
+                self.bitboards = self.bitboards.after_move(&self.board, from, to);
+                self.board.make_move(from, to);
+            }
+            Move::Promote(from, to, piece_type) => {
+                self.just_advanced_two = None;
+                forfeit_captured_rook(&mut self.castling, !self.turn, to);
+                self.bitboards = self
+                    .bitboards
+                    .after_promotion(&self.board, from, to, piece_type);
+                self.board.make_promotion(from, to, piece_type);
+            }
+        }
+        self.turn = !self.turn;
+        self.attack_table = OnceLock::new();
+        undo
+    }
+
+    /// takes back the move `make` returned `undo` for, which must be the last one made
+    pub fn unmake(&mut self, undo: Undo) {
+        let Undo {
+            r#move,
+            captured,
+            castled,
+            just_advanced_two,
+            castling,
+            kings,
+            bitboards,
+            hash,
+            halfmove_clock,
+            fullmove_number,
+        } = undo;
+        let (from, to) = (r#move.from(), r#move.to());
+        self.turn = !self.turn;
+        if let Some((king_to, rook_from, rook_to)) = castled {
+            // in Chess960 the king and rook may each end up where the other started
+            let king = self.board[king_to].take();
+            let rook = self.board[rook_to].take();
+            self.board[from] = king;
+            self.board[rook_from] = rook;
+        } else {
+            let piece = self.board[to].take().map(|piece| match r#move {
+                Move::Normal(..) => piece,
+                Move::Promote(..) => Piece {
+                    piece: PieceType::Pawn,
+                    color: piece.color,
+                },
+            });
+            self.board[from] = piece;
+            if let Some((position, captured)) = captured {
+                self.board[position] = Some(captured);
+            }
+        }
+        self.just_advanced_two = just_advanced_two;
+        self.castling = castling;
+        self.kings = kings;
+        self.bitboards = bitboards;
+        self.hash = hash;
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
+        self.attack_table = OnceLock::new();
+    }
+
+    /// plays `r#move` without checking it at all, not even with debug assertions,
+    /// for the search's hot path.
+    /// REQUIRES: `r#move` is legal
+    pub fn apply_move_unchecked(&self, r#move: Move) -> Self {
+        match r#move {
+            Move::Normal(from, to) => self.move_unchecked(from, to),
+            Move::Promote(from, to, piece_type) => self.promote_unchecked(from, to, piece_type),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use rayon::prelude::*;
+
+use crate::game::{Game, Move};
+
+impl Game {
+    /// every move in this game, counting each promotion choice, and the game that follows it
+    fn successors(&self) -> Vec<(Move, Game)> {
+        self.legal_moves()
+            .into_iter()
+            .map(|r#move| (r#move, self.apply_move_unchecked(r#move)))
+            .collect()
+    }
+
+    /// number of distinct move sequences `depth` plies long, for checking move generation
+    /// against known counts
+    pub fn perft(&self, depth: usize) -> u64 {
+        match depth {
+            0 => 1,
+            // no need to make the final moves just to count them
+            1 => self.legal_move_count() as u64,
+            _ => self
+                .successors()
+                .iter()
+                .map(|(_, game)| game.perft(depth - 1))
+                .sum(),
+        }
+    }
+
+    /// `perft`, with the subtree under each first move counted on rayon's thread pool
+    pub fn perft_parallel(&self, depth: usize) -> u64 {
+        match depth {
+            0 => 1,
+            _ => self
+                .successors()
+                .par_iter()
+                .map(|(_, game)| game.perft(depth - 1))
+                .sum(),
+        }
+    }
+
+    /// `perft`, broken down by first move, for finding where move generation goes wrong by
+    /// comparing with another engine's counts. Empty at depth 0.
+    pub fn perft_split(&self, depth: usize) -> Vec<(Move, u64)> {
+        match depth {
+            0 => Vec::new(),
+            _ => self
+                .successors()
+                .into_par_iter()
+                .map(|(r#move, game)| (r#move, game.perft(depth - 1)))
+                .collect(),
+        }
+    }
+}
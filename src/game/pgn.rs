@@ -0,0 +1,249 @@
+use std::{
+    fmt::Display,
+    str::{Chars, FromStr},
+};
+
+use crate::game::{FenError, Game, Move, Outcome, PieceColor};
+
+/// a game recorded in Portable Game Notation: where it started and the moves played from there
+pub struct Pgn {
+    pub start: Game,
+    pub moves: Vec<Move>,
+    /// how the game ended, if not on the board, e.g. by resignation
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PgnError {
+    Fen(FenError),
+    IllegalMove { ply: usize, san: String },
+    Unterminated(char),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::Fen(error) => write!(f, "invalid FEN tag: {error}"),
+            PgnError::IllegalMove { ply, san } => {
+                write!(f, "{san:?} is not a legal move at ply {}", ply + 1)
+            }
+            PgnError::Unterminated(c) => write!(f, "unterminated {c:?}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// a tag pair such as `[Event "Casual"]`, as name and value
+type Tag = (String, String);
+
+/// consumes `chars` up to and including the `close` that matches an `open` just consumed,
+/// returning what was in between
+fn skip_past(chars: &mut Chars, open: char, close: char) -> Result<String, PgnError> {
+    let mut skipped = String::new();
+    let mut depth = 1;
+    for c in chars.by_ref() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(skipped);
+            }
+        }
+        skipped.push(c);
+    }
+    Err(PgnError::Unterminated(open))
+}
+
+/// splits movetext into tokens, dropping comments and variations and collecting tag pairs
+fn tokenize(pgn: &str) -> Result<(Vec<Tag>, Vec<String>), PgnError> {
+    let mut tags = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = pgn.chars();
+    while let Some(c) = chars.next() {
+        let delimiter = matches!(c, '[' | '{' | ';' | '(') || c.is_whitespace();
+        if delimiter && !token.is_empty() {
+            tokens.push(std::mem::take(&mut token));
+        }
+        match c {
+            '[' => {
+                let tag = skip_past(&mut chars, '[', ']')?;
+                if let Some((name, value)) = tag.trim().split_once(char::is_whitespace) {
+                    let value = value.trim().trim_matches('"');
+                    tags.push((name.to_string(), value.to_string()));
+                }
+            }
+            // comments do not nest, but braces cannot appear within them anyway
+            '{' => {
+                skip_past(&mut chars, '{', '}')?;
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => {
+                skip_past(&mut chars, '(', ')')?;
+            }
+            c if c.is_whitespace() => {}
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok((tags, tokens))
+}
+
+impl FromStr for Pgn {
+    type Err = PgnError;
+
+    /// reads the first game in `pgn`, starting from its `FEN` tag if it has one
+    fn from_str(pgn: &str) -> Result<Self, PgnError> {
+        let (tags, tokens) = tokenize(pgn)?;
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Game::from_fen(fen).map_err(PgnError::Fen)?,
+            None => Game::new(),
+        };
+        let mut game = start.clone();
+        let mut moves = Vec::new();
+        for token in tokens {
+            if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                break;
+            }
+            // move numbers may run into the move, as in `1.e4` or `1...e5`
+            let san = match token.rfind('.') {
+                Some(i) => &token[i + 1..],
+                None => &token,
+            };
+            if san.is_empty() || san.starts_with('$') {
+                continue;
+            }
+            let r#move = game.parse_san(san).ok_or_else(|| PgnError::IllegalMove {
+                ply: moves.len(),
+                san: san.to_string(),
+            })?;
+            game = game.apply_move_unchecked(r#move);
+            moves.push(r#move);
+        }
+        Ok(Self {
+            start,
+            moves,
+            outcome: None,
+        })
+    }
+}
+
+/// the token that ends the movetext of a game with this outcome
+fn result(outcome: Option<Outcome>) -> &'static str {
+    match outcome.map(Outcome::winner) {
+        Some(Some(PieceColor::White)) => "1-0",
+        Some(Some(PieceColor::Black)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// writes the game with a `FEN` tag if it did not start from the usual position, its moves in
+/// SAN, and its result from `outcome` or else as far as the final position shows it
+impl Display for Pgn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start != Game::new() {
+            writeln!(f, "[SetUp \"1\"]")?;
+            writeln!(f, "[FEN \"{}\"]", self.start.to_fen())?;
+            writeln!(f)?;
+        }
+        let mut game = self.start.clone();
+        let mut number = 1;
+        for (ply, &r#move) in self.moves.iter().enumerate() {
+            match game.turn() {
+                PieceColor::White => write!(f, "{number}. ")?,
+                PieceColor::Black if ply == 0 => write!(f, "{number}... ")?,
+                PieceColor::Black => {}
+            }
+            write!(f, "{} ", game.san(r#move))?;
+            if game.turn() == PieceColor::Black {
+                number += 1;
+            }
+            game = game.apply_move_unchecked(r#move);
+        }
+        write!(f, "{}", result(self.outcome.or_else(|| game.status())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Pgn, PgnError};
+    use crate::game::{square, Move, Outcome, PieceColor, PieceType};
+
+    #[test]
+    fn parse() {
+        let pgn: Pgn = r#"
+            [Event "Casual"]
+            [White "?"]
+
+            1. e4 e5 2.Nf3 {developing} Nc6 (2... d6 3. d4) 3. Bc4 $1 Nf6?!
+            4. Ng5 d5 5. exd5 Nxd5?? 6. Nxf7 ; the fried liver
+            6... Kxf7 7. 0-0 1-0 8. Qf3+
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(pgn.moves.len(), 13);
+        let game = pgn
+            .moves
+            .iter()
+            .fold(pgn.start, |game, &r#move| game.apply(r#move).unwrap());
+        assert_eq!(
+            game.to_fen(),
+            "r1bq1b1r/ppp2kpp/2n5/3np3/2B5/8/PPPP1PPP/RNBQ1RK1 b - - 1 7"
+        );
+    }
+
+    #[test]
+    fn fen_tag() {
+        let pgn: Pgn = r#"[SetUp "1"] [FEN "7k/4P3/8/8/8/8/8/K7 w - - 0 1"] 1. e8=R+ Kg7 *"#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            pgn.moves[0],
+            Move::Promote(square("e7"), square("e8"), PieceType::Rook)
+        );
+        assert_eq!(pgn.moves.len(), 2);
+        assert_eq!(pgn.start.to_fen(), "7k/4P3/8/8/8/8/8/K7 w - - 0 1");
+    }
+
+    #[test]
+    fn write() {
+        let pgn: Pgn = "1. f3 e5 2. g4 Qh4#".parse().unwrap();
+        assert_eq!(pgn.to_string(), "1. f3 e5 2. g4 Qh4# 0-1");
+        let pgn: Pgn = r#"[FEN "7k/4P3/8/8/8/8/8/K7 b - - 0 1"] 1... Kg7 2. e8=N+ *"#
+            .parse()
+            .unwrap();
+        // a lone knight cannot mate
+        assert_eq!(
+            pgn.to_string(),
+            "[SetUp \"1\"]\n[FEN \"7k/4P3/8/8/8/8/8/K7 b - - 0 1\"]\n\n1... Kg7 2. e8=N+ 1/2-1/2"
+        );
+        assert_eq!(pgn.to_string().parse::<Pgn>().unwrap().moves, pgn.moves);
+
+        let mut pgn: Pgn = "1. e4".parse().unwrap();
+        assert_eq!(pgn.to_string(), "1. e4 *");
+        pgn.outcome = Some(Outcome::Resignation(PieceColor::Black));
+        assert_eq!(pgn.to_string(), "1. e4 1-0");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "1. e4 e4".parse::<Pgn>().err(),
+            Some(PgnError::IllegalMove {
+                ply: 1,
+                san: "e4".to_string()
+            })
+        );
+        assert_eq!(
+            "1. e4 {unfinished".parse::<Pgn>().err(),
+            Some(PgnError::Unterminated('{'))
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use std::{fmt::Display, ops::Not};
+use std::{fmt::Display, ops::Not, str::FromStr};
 
 use ratatui::prelude::*;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -23,9 +28,39 @@ impl PieceType {
             Self::King => "♚",
         }
     }
+
+    /// the uppercase letter FEN and SAN use for this type of piece
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Pawn => 'P',
+            Self::Knight => 'N',
+            Self::Bishop => 'B',
+            Self::Rook => 'R',
+            Self::Queen => 'Q',
+            Self::King => 'K',
+        }
+    }
+
+    /// parses `to_char`'s letters in either case
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(Self::Pawn),
+            'N' => Some(Self::Knight),
+            'B' => Some(Self::Bishop),
+            'R' => Some(Self::Rook),
+            'Q' => Some(Self::Queen),
+            'K' => Some(Self::King),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum PieceColor {
     White,
     Black,
@@ -65,6 +100,19 @@ impl Not for PieceColor {
     }
 }
 
+impl Display for PieceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PieceType::Pawn => write!(f, "Pawn"),
+            PieceType::Knight => write!(f, "Knight"),
+            PieceType::Bishop => write!(f, "Bishop"),
+            PieceType::Rook => write!(f, "Rook"),
+            PieceType::Queen => write!(f, "Queen"),
+            PieceType::King => write!(f, "King"),
+        }
+    }
+}
+
 impl Display for PieceColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -74,7 +122,32 @@ impl Display for PieceColor {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParsePieceColorError(String);
+
+impl Display for ParsePieceColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid color {:?}: expected white or black", self.0)
+    }
+}
+
+impl std::error::Error for ParsePieceColorError {}
+
+/// `white` or `black`, in any case
+impl FromStr for PieceColor {
+    type Err = ParsePieceColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "white" => Ok(PieceColor::White),
+            "black" => Ok(PieceColor::Black),
+            _ => Err(ParsePieceColorError(s.to_string())),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub color: PieceColor,
     pub piece: PieceType,
@@ -84,4 +157,80 @@ impl Piece {
     pub fn render(&self) -> Span<'static> {
         Span::raw(self.piece.render()).fg(self.color.render())
     }
+
+    /// the FEN letter for this piece: uppercase for white, lowercase for black
+    pub fn to_fen_char(self) -> char {
+        match self.color {
+            PieceColor::White => self.piece.to_char(),
+            PieceColor::Black => self.piece.to_char().to_ascii_lowercase(),
+        }
+    }
+
+    /// the Unicode chess symbol for this piece, outlined for white and filled for black
+    pub fn to_unicode(self) -> char {
+        let (white, black) = match self.piece {
+            PieceType::Pawn => ('♙', '♟'),
+            PieceType::Knight => ('♘', '♞'),
+            PieceType::Bishop => ('♗', '♝'),
+            PieceType::Rook => ('♖', '♜'),
+            PieceType::Queen => ('♕', '♛'),
+            PieceType::King => ('♔', '♚'),
+        };
+        match self.color {
+            PieceColor::White => white,
+            PieceColor::Black => black,
+        }
+    }
+
+    pub fn from_fen_char(c: char) -> Option<Self> {
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let piece = PieceType::from_char(c)?;
+        Some(Self { color, piece })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Piece, PieceColor, PieceType};
+
+    const PIECE_TYPES: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
+    #[test]
+    fn chars() {
+        for (piece, c) in PIECE_TYPES.into_iter().zip("PNBRQK".chars()) {
+            assert_eq!(piece.to_char(), c);
+            assert_eq!(PieceType::from_char(c), Some(piece));
+            assert_eq!(PieceType::from_char(c.to_ascii_lowercase()), Some(piece));
+            for (color, c) in [
+                (PieceColor::White, c),
+                (PieceColor::Black, c.to_ascii_lowercase()),
+            ] {
+                let piece = Piece { color, piece };
+                assert_eq!(piece.to_fen_char(), c);
+                assert_eq!(Piece::from_fen_char(c), Some(piece));
+            }
+        }
+        for c in (char::MIN..='\u{ff}').filter(|c| !"PNBRQKpnbrqk".contains(*c)) {
+            assert_eq!(PieceType::from_char(c), None, "{c:?}");
+            assert_eq!(Piece::from_fen_char(c), None, "{c:?}");
+        }
+    }
+
+    #[test]
+    fn parse_color() {
+        assert_eq!("white".parse(), Ok(PieceColor::White));
+        assert_eq!("Black".parse(), Ok(PieceColor::Black));
+        assert!("w".parse::<PieceColor>().is_err());
+    }
 }
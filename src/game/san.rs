@@ -0,0 +1,81 @@
+use crate::game::{piece::PieceType, Game, Move, Outcome, Position};
+
+fn file_letter(position: Position) -> char {
+    (b'a' + position.file()) as char
+}
+
+fn rank_digit(position: Position) -> char {
+    (b'1' + position.rank()) as char
+}
+
+impl Game {
+    /// standard algebraic notation (e.g. `Nbd7`, `exd5`, `e8=Q+`, `O-O`) for a move in this game.
+    /// REQUIRES: the move is legal
+    pub fn san(&self, r#move: Move) -> String {
+        let (from, to) = (r#move.from(), r#move.to());
+        let piece = self.board.get(from).expect("Game::san precondition").piece;
+        let mut san = String::new();
+        if self.is_castling(from, to) {
+            san.push_str(if to.file() > from.file() {
+                "O-O"
+            } else {
+                "O-O-O"
+            });
+        } else if piece == PieceType::Pawn {
+            if from.file() != to.file() {
+                san.push(file_letter(from));
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+        } else {
+            san.push(piece.to_char());
+            // other pieces of the same type that could also move to `to`
+            let others: Vec<Position> = self
+                .moves()
+                .filter(|&(other, ref moves)| {
+                    other != from
+                        && self.board.get(other).is_some_and(|p| p.piece == piece)
+                        && moves.contains(&to)
+                })
+                .map(|(other, _)| other)
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|other| other.file() != from.file()) {
+                    san.push(file_letter(from));
+                } else if others.iter().all(|other| other.rank() != from.rank()) {
+                    san.push(rank_digit(from));
+                } else {
+                    san.push_str(&from.to_string());
+                }
+            }
+            if self.is_capture(from, to) {
+                san.push('x');
+            }
+            san.push_str(&to.to_string());
+        }
+        if let Some(piece_type) = r#move.promotion() {
+            san.push('=');
+            san.push(piece_type.to_char());
+        }
+        let after = self.apply_move_unchecked(r#move);
+        match after.status() {
+            Some(Outcome::Win(_)) => san.push('#'),
+            _ if after.check() => san.push('+'),
+            _ => {}
+        }
+        san
+    }
+
+    /// the legal move written `san` in standard algebraic notation, as `Game::san` writes it.
+    /// Check and mate marks and annotations such as `!?` are optional, and castling may be
+    /// written with zeros.
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        // squares have no zeros, so only castling is affected
+        let normalize = |san: &str| san.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+        let san = normalize(san);
+        self.legal_moves().into_iter().find(|&r#move| {
+            let written = self.san(r#move);
+            normalize(&written) == san
+        })
+    }
+}
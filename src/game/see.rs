@@ -0,0 +1,94 @@
+use std::sync::OnceLock;
+
+use crate::{
+    ai::value,
+    game::{Game, Piece, PieceColor, PieceType, Position},
+};
+
+impl Game {
+    /// static exchange evaluation: the material in centipawns the player to move gains by
+    /// capturing on `to` with the piece at `from`, if both sides then keep recapturing there
+    /// with their least valuable piece for as long as it pays. A piece behind another on the
+    /// same line joins in once the one in front has captured. Pins are not considered.
+    /// REQUIRES: the move is a legal capture
+    pub fn see(&self, from: Position, to: Position) -> i32 {
+        let centipawns = |piece: PieceType| value(piece) * 100;
+        // only the board changes as the pieces capture, and every capture lands on `to`, so
+        // what stands there does not matter
+        let mut scratch = Self {
+            turn: self.turn,
+            board: self.board,
+            just_advanced_two: None,
+            castling: self.castling,
+            kings: self.kings,
+            bitboards: self.bitboards,
+            attack_table: OnceLock::new(),
+            // not kept up to date, as nothing here looks at it
+            hash: self.hash,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        };
+        let mut attacker = scratch.board[from].take().expect("Game::see precondition");
+        // en passant captures a pawn on a vacant square
+        let victim = match self.board.get(to) {
+            Some(victim) => victim.piece,
+            None => {
+                scratch.board[Position::new(from.rank(), to.file())] = None;
+                PieceType::Pawn
+            }
+        };
+        // `gains[i]` = what the side making the ith capture gains if the exchange stops after it
+        let mut gains = vec![centipawns(victim)];
+        let mut side = !self.turn;
+        while let Some((position, piece)) = scratch.least_valuable_attacker(side, to) {
+            scratch.board[position] = None;
+            // the king may not recapture into check
+            if piece.piece == PieceType::King
+                && scratch.least_valuable_attacker(!side, to).is_some()
+            {
+                break;
+            }
+            let last = *gains.last().expect("nonempty");
+            gains.push(centipawns(attacker.piece) - last);
+            attacker = piece;
+            side = !side;
+        }
+        // each side may decline to recapture if it would lose by it
+        while gains.len() > 1 {
+            let gain = gains.pop().expect("nonempty");
+            let last = gains.last_mut().expect("nonempty");
+            *last = -(-*last).max(gain);
+        }
+        gains[0]
+    }
+
+    /// static exchange evaluation of the square `to`: the material in centipawns the player to
+    /// move gains by capturing there with its least valuable piece and exchanging as in `see`,
+    /// or 0 if it has no capture there that pays
+    pub fn see_on(&self, to: Position) -> i32 {
+        match self.board.get(to) {
+            Some(piece) if piece.color != self.turn => self
+                .least_valuable_attacker(self.turn, to)
+                .map_or(0, |(from, _)| self.see(from, to).max(0)),
+            _ => 0,
+        }
+    }
+
+    /// `color`'s least valuable piece attacking `target` other than one standing on it, with
+    /// the king last
+    fn least_valuable_attacker(
+        &self,
+        color: PieceColor,
+        target: Position,
+    ) -> Option<(Position, Piece)> {
+        self.board
+            .iter(color)
+            .filter(|&(position, piece)| {
+                position != target && self.attacks_from(position, piece, target)
+            })
+            .min_by_key(|(_, piece)| match piece.piece {
+                PieceType::King => i32::MAX,
+                piece => value(piece),
+            })
+    }
+}
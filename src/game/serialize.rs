@@ -0,0 +1,135 @@
+//! serde support, behind the `serde` feature. Positions are written as algebraic names,
+//! boards as FEN piece placements, and games as objects with one key per FEN field.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::game::{
+    board::{position::Position, Board},
+    fen::{format_castling, format_placement, parse_placement},
+    piece::PieceColor,
+    Game,
+};
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_placement(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        parse_placement(&String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
+/// the serialized form of a `Game`
+#[derive(Serialize, Deserialize)]
+struct FenFields {
+    placement: String,
+    active_color: PieceColor,
+    castling: String,
+    en_passant: Option<Position>,
+    #[serde(default)]
+    halfmove_clock: u32,
+    #[serde(default = "first_move")]
+    fullmove_number: u32,
+}
+
+fn first_move() -> u32 {
+    1
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FenFields {
+            placement: format_placement(&self.board),
+            active_color: self.turn,
+            castling: format_castling(&self.castling, &self.board),
+            en_passant: self.en_passant_square(),
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    /// validates the fields as `Game::from_fen` would
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let FenFields {
+            placement,
+            active_color,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        } = FenFields::deserialize(deserializer)?;
+        let active_color = match active_color {
+            PieceColor::White => 'w',
+            PieceColor::Black => 'b',
+        };
+        let en_passant = en_passant.map_or("-".to_string(), |target| target.to_string());
+        let fen = format!(
+            "{placement} {active_color} {castling} {en_passant} {halfmove_clock} {fullmove_number}"
+        );
+        Game::from_fen(&fen).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game::{Game, PieceColor, PieceType, Position};
+
+    #[test]
+    fn names() {
+        let e4: Position = "e4".parse().unwrap();
+        assert_eq!(serde_json::to_string(&e4).unwrap(), r#""e4""#);
+        assert_eq!(serde_json::from_str::<Position>(r#""e4""#).unwrap(), e4);
+        assert!(serde_json::from_str::<Position>(r#""e9""#).is_err());
+        assert_eq!(
+            serde_json::to_string(&PieceColor::White).unwrap(),
+            r#""white""#
+        );
+        assert_eq!(
+            serde_json::to_string(&PieceType::Knight).unwrap(),
+            r#""knight""#
+        );
+    }
+
+    #[test]
+    fn game_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // en passant
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1",
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1",
+            // partial and absent castling rights
+            "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let game = Game::from_fen(fen).expect("valid fen");
+            let json = serde_json::to_string(&game).unwrap();
+            let round_trip: Game = serde_json::from_str(&json).unwrap();
+            assert!(round_trip == game, "{json}");
+        }
+        let json = serde_json::to_string(&Game::new()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"placement":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR","active_color":"white","castling":"KQkq","en_passant":null,"halfmove_clock":0,"fullmove_number":1}"#
+        );
+    }
+}
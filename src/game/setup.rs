@@ -0,0 +1,186 @@
+use std::{fmt::Display, sync::OnceLock};
+
+use crate::game::{
+    bitboards::Bitboards,
+    board::{
+        position::{Movement, Position},
+        Board,
+    },
+    castling::Castling,
+    kings::Kings,
+    piece::{Piece, PieceColor, PieceType},
+    Game,
+};
+
+/// why a board setup is not a position that can arise in a game
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PositionError {
+    KingCount(PieceColor),
+    PawnOnBackRank(Position),
+    /// more pieces than the given color starts with, counting each piece beyond the starting
+    /// number as a promoted pawn
+    TooManyPieces(PieceColor),
+    /// the given color may castle, but its king or the rook is not where it started
+    CastlingRights(PieceColor),
+    /// no pawn can just have advanced two squares past the given square
+    EnPassant(Position),
+    /// the given color is in check, but it is the other's turn, so its king could be captured
+    OpponentInCheck(PieceColor),
+    /// the fullmove number is 0, but it starts at 1
+    FullmoveNumber,
+    /// the given halfmove clock is not 0 although a pawn has just advanced two
+    HalfmoveClock(u32),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PositionError::KingCount(color) => write!(f, "{color} must have exactly one king"),
+            PositionError::PawnOnBackRank(position) => {
+                write!(f, "pawn on back rank at {position}")
+            }
+            PositionError::TooManyPieces(color) => write!(f, "{color} has too many pieces"),
+            PositionError::CastlingRights(color) => write!(
+                f,
+                "{color} may castle, but its king or rook is not on its starting square"
+            ),
+            PositionError::EnPassant(position) => {
+                write!(f, "no pawn can be captured en passant on {position}")
+            }
+            PositionError::OpponentInCheck(color) => {
+                write!(f, "{color} is in check but it is {}'s turn", !*color)
+            }
+            PositionError::FullmoveNumber => write!(f, "the fullmove number starts at 1"),
+            PositionError::HalfmoveClock(clock) => write!(
+                f,
+                "halfmove clock {clock} should be 0 just after a pawn advanced two"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+/// how many of each piece but the king a side starts with
+fn starting_count(piece: PieceType) -> usize {
+    match piece {
+        PieceType::Pawn => 8,
+        PieceType::Knight | PieceType::Bishop | PieceType::Rook => 2,
+        PieceType::Queen | PieceType::King => 1,
+    }
+}
+
+impl Game {
+    /// a game from the given position, with `en_passant` the square behind a pawn that has just
+    /// advanced two, `halfmove_clock` the plies since the last capture or pawn move and
+    /// `fullmove_number` the number of the move being played, all as FEN records them.
+    /// The position must be one that can arise in a game:
+    /// - each side has exactly one king,
+    /// - no pawn stands on the first or last rank,
+    /// - no side has more pieces than it starts with, once each piece beyond the starting
+    ///   number is put down to a promoted pawn,
+    /// - a side that may castle has its king and the rook on the files `castling` says they
+    ///   start on, on its back rank,
+    /// - the en passant square, if any, has an empty square in front of it and the pawn of the
+    ///   player not to move behind it,
+    /// - the player not to move is not in check,
+    /// - the fullmove number is at least 1, and
+    /// - the halfmove clock is 0 if there is an en passant square.
+    pub fn from_position(
+        board: Board,
+        turn: PieceColor,
+        castling: Castling,
+        en_passant: Option<Position>,
+        halfmove_clock: u32,
+        fullmove_number: u32,
+    ) -> Result<Self, PositionError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let count = |piece| board.iter_by_type(piece, color).count();
+            if count(PieceType::King) != 1 {
+                return Err(PositionError::KingCount(color));
+            }
+            if let Some(position) = board
+                .iter_by_type(PieceType::Pawn, color)
+                .find(|position| matches!(position.rank(), 0 | 7))
+            {
+                return Err(PositionError::PawnOnBackRank(position));
+            }
+            let promoted: usize = [
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Rook,
+                PieceType::Queen,
+            ]
+            .into_iter()
+            .map(|piece| count(piece).saturating_sub(starting_count(piece)))
+            .sum();
+            if count(PieceType::Pawn) + promoted > starting_count(PieceType::Pawn) {
+                return Err(PositionError::TooManyPieces(color));
+            }
+
+            let files = castling.files();
+            let rank = color.piece_starting_rank();
+            let on =
+                |file, piece| board.get(Position::new(rank, file)) == Some(Piece { color, piece });
+            let rights = castling[color];
+            if (rights.can_castle_queenside() || rights.can_castle_kingside())
+                && !on(files.king, PieceType::King)
+                || rights.can_castle_queenside() && !on(files.queenside_rook, PieceType::Rook)
+                || rights.can_castle_kingside() && !on(files.kingside_rook, PieceType::Rook)
+            {
+                return Err(PositionError::CastlingRights(color));
+            }
+        }
+
+        // we track the pawn itself rather than the square behind it
+        let just_advanced_two = match en_passant {
+            None => None,
+            Some(target) => {
+                let error = PositionError::EnPassant(target);
+                let pawn = target.pawn(!turn).ok_or(error)?;
+                let from = target.pawn(turn).ok_or(error)?;
+                let expected_rank = match turn {
+                    PieceColor::White => 4,
+                    PieceColor::Black => 3,
+                };
+                if pawn.rank() != expected_rank
+                    || board.get(pawn)
+                        != Some(Piece {
+                            color: !turn,
+                            piece: PieceType::Pawn,
+                        })
+                    || !board.is_vacant(target)
+                    || !board.is_vacant(from)
+                {
+                    return Err(error);
+                }
+                Some(pawn)
+            }
+        };
+
+        if fullmove_number == 0 {
+            return Err(PositionError::FullmoveNumber);
+        }
+        if just_advanced_two.is_some() && halfmove_clock != 0 {
+            return Err(PositionError::HalfmoveClock(halfmove_clock));
+        }
+
+        let mut game = Self {
+            turn,
+            board,
+            just_advanced_two,
+            castling,
+            kings: Kings::find(&board),
+            bitboards: Bitboards::find(&board),
+            attack_table: OnceLock::new(),
+            hash: 0,
+            halfmove_clock,
+            fullmove_number,
+        };
+        game.hash = game.zobrist();
+        if game.attacks(turn, game.kings[!turn]) {
+            return Err(PositionError::OpponentInCheck(!turn));
+        }
+        Ok(game)
+    }
+}
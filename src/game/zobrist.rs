@@ -0,0 +1,197 @@
+use crate::game::{
+    castling::Castling,
+    forfeit_captured_rook,
+    piece::{Piece, PieceColor, PieceType},
+    Game, Move, Position,
+};
+
+struct Keys {
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    /// white queenside, white kingside, black queenside, black kingside
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+/// the next of a sequence of pseudorandom numbers, advancing `state`
+pub(crate) const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl Keys {
+    const fn generate() -> Self {
+        let mut state = 0;
+        let mut pieces = [[[0; 64]; 6]; 2];
+        let mut color = 0;
+        while color < 2 {
+            let mut piece = 0;
+            while piece < 6 {
+                let mut square = 0;
+                while square < 64 {
+                    pieces[color][piece][square] = splitmix64(&mut state);
+                    square += 1;
+                }
+                piece += 1;
+            }
+            color += 1;
+        }
+        let black_to_move = splitmix64(&mut state);
+        let mut castling = [0; 4];
+        let mut i = 0;
+        while i < 4 {
+            castling[i] = splitmix64(&mut state);
+            i += 1;
+        }
+        let mut en_passant_file = [0; 8];
+        let mut i = 0;
+        while i < 8 {
+            en_passant_file[i] = splitmix64(&mut state);
+            i += 1;
+        }
+        Self {
+            pieces,
+            black_to_move,
+            castling,
+            en_passant_file,
+        }
+    }
+}
+
+const KEYS: Keys = Keys::generate();
+
+fn piece_index(piece: PieceType) -> usize {
+    match piece {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+fn square_key(piece: Piece, position: Position) -> u64 {
+    KEYS.pieces[piece.color as usize][piece_index(piece.piece)][position.0 as usize]
+}
+
+fn castling_key(castling: &Castling) -> u64 {
+    let mut key = 0;
+    for color in [PieceColor::White, PieceColor::Black] {
+        let offset = 2 * color as usize;
+        if castling[color].can_castle_queenside() {
+            key ^= KEYS.castling[offset];
+        }
+        if castling[color].can_castle_kingside() {
+            key ^= KEYS.castling[offset + 1];
+        }
+    }
+    key
+}
+
+impl Game {
+    /// Zobrist hash of the position: equal positions have equal hashes.
+    /// It is kept up to date as moves are made, a few keys at a time, so asking costs nothing.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// `hash`, computed from the whole position
+    pub(crate) fn zobrist(&self) -> u64 {
+        let mut key = castling_key(&self.castling);
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (position, piece) in self.board.iter(color) {
+                key ^= square_key(piece, position);
+            }
+        }
+        if self.turn == PieceColor::Black {
+            key ^= KEYS.black_to_move;
+        }
+        if let Some(position) = self.just_advanced_two {
+            key ^= KEYS.en_passant_file[position.file() as usize];
+        }
+        key
+    }
+
+    /// `self.apply_move_unchecked(r#move).hash()`, without making the move
+    /// REQUIRES: the move is legal
+    pub fn hash_after(&self, r#move: Move) -> u64 {
+        let Move::Promote(from, to, piece_type) = r#move else {
+            return self.hash_after_move(r#move.from(), r#move.to());
+        };
+        let pawn = self.board.get(from).expect("Game::hash_after precondition");
+        let promoted = Piece {
+            piece: piece_type,
+            color: pawn.color,
+        };
+        let mut key = self.hash ^ square_key(pawn, from) ^ square_key(promoted, to);
+        if let Some(captured) = self.board.get(to) {
+            key ^= square_key(captured, to);
+        }
+        let mut castling = self.castling;
+        forfeit_captured_rook(&mut castling, !pawn.color, to);
+        key ^= castling_key(&self.castling) ^ castling_key(&castling);
+        key ^ self.hash_of_turn()
+    }
+
+    /// `self.null_move().hash()`
+    pub(crate) fn hash_after_null_move(&self) -> u64 {
+        self.hash ^ self.hash_of_turn()
+    }
+
+    /// the keys for whose turn it is and for en passant, which every move hashes out, and
+    /// then hashes in those for the other side's turn
+    fn hash_of_turn(&self) -> u64 {
+        let mut key = KEYS.black_to_move;
+        if let Some(position) = self.just_advanced_two {
+            key ^= KEYS.en_passant_file[position.file() as usize];
+        }
+        key
+    }
+
+    /// `self.r#move(from, to).hash()`, without making the move.
+    /// REQUIRES: the move is legal and not a promotion
+    pub fn hash_after_move(&self, from: Position, to: Position) -> u64 {
+        debug_assert!(!self.is_promotion(from, to), "{from} -> {to}");
+        let piece = self
+            .board
+            .get(from)
+            .expect("Game::hash_after_move precondition");
+        let mut key = self.hash ^ square_key(piece, from);
+        if let Some((king_to, rook_from, rook_to)) = self.board.castling(from, to) {
+            let rook = Piece {
+                piece: PieceType::Rook,
+                color: piece.color,
+            };
+            key ^= square_key(piece, king_to)
+                ^ square_key(rook, rook_from)
+                ^ square_key(rook, rook_to);
+        } else if let Some(captured) = self.board.get(to) {
+            key ^= square_key(piece, to) ^ square_key(captured, to);
+        } else if piece.piece == PieceType::Pawn && from.file() != to.file() {
+            let pawn = self
+                .just_advanced_two
+                .expect("Game::just_advanced_two invariant");
+            key ^= square_key(piece, to)
+                ^ square_key(
+                    Piece {
+                        piece: PieceType::Pawn,
+                        color: !piece.color,
+                    },
+                    pawn,
+                );
+        } else {
+            key ^= square_key(piece, to);
+        }
+        key ^=
+            castling_key(&self.castling) ^ castling_key(&self.castling_after_move(piece, from, to));
+        key ^= self.hash_of_turn();
+        if piece.piece == PieceType::Pawn && from.rank().abs_diff(to.rank()) == 2 {
+            key ^= KEYS.en_passant_file[to.file() as usize];
+        }
+        key
+    }
+}
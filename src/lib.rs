@@ -1,3 +1,5 @@
 pub mod ai;
 pub mod game;
+pub mod openingbook;
 pub mod tui;
+pub mod uci;
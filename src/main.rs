@@ -1,4 +1,9 @@
-use std::process::ExitCode;
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
 use clap::Parser;
 use ratatui::crossterm::{
@@ -6,16 +11,278 @@ use ratatui::crossterm::{
     ExecutableCommand,
 };
 
-use chess::tui::Tui;
+use chess::{
+    ai::Engine,
+    game::{Game, Move, Pgn, PieceColor, PieceType, Position},
+    openingbook::PolyglotBook,
+    tui::{Theme, TimeControl, Tui},
+};
 
 #[derive(Parser)]
 struct Args {
-    ai: Option<usize>,
+    /// have the AI play white, searching this many plies deep
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    white_ai: Option<usize>,
+    /// have the AI play black, searching this many plies deep
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    black_ai: Option<usize>,
+    /// the least time the AI takes over each move, in milliseconds, e.g. to watch it play itself
+    #[arg(long, default_value_t = 0)]
+    delay_ms: u64,
+    /// instead of running the TUI, have the AI play both sides from the starting position, or
+    /// `--fen`, printing each move and then the result, final position and PGN
+    #[arg(long, conflicts_with_all = ["replay", "uci"])]
+    selfplay: bool,
+    /// how many plies deep the AI searches for white in `--selfplay`
+    #[arg(
+        long,
+        requires = "selfplay",
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    white_depth: usize,
+    /// how many plies deep the AI searches for black in `--selfplay`
+    #[arg(
+        long,
+        requires = "selfplay",
+        default_value_t = 4,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    black_depth: usize,
+    /// instead of running the TUI, count the move sequences this many plies long from the
+    /// starting position, or `--fen`, printing the count under each first move and the total
+    #[arg(
+        long,
+        conflicts_with_all = ["replay", "uci", "selfplay"],
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+    )]
+    perft: Option<usize>,
+    /// speak the Universal Chess Interface protocol on stdin/stdout instead of running the TUI
+    #[arg(long)]
+    uci: bool,
+    /// speak each move aloud using `say` (macOS) or `espeak` (elsewhere)
+    #[arg(long)]
+    announce_moves: bool,
+    /// start from this position in Forsyth–Edwards Notation instead of the usual one
+    #[arg(long, conflicts_with = "replay")]
+    fen: Option<String>,
+    /// start from this Chess960 starting position instead of the usual one, numbered from 0 to
+    /// 959 as is standard, in which 518 is the usual one
+    #[arg(
+        long,
+        value_name = "ID",
+        conflicts_with_all = ["fen", "replay"],
+        value_parser = clap::builder::RangedU64ValueParser::<u16>::new().range(..960),
+    )]
+    chess960: Option<u16>,
+    /// instead of running the TUI, play the move from this square to `--to` from the starting
+    /// position, or `--fen`, and print it and the resulting position
+    #[arg(long, requires = "to")]
+    from: Option<Position>,
+    /// see `--from`
+    #[arg(long, requires = "from")]
+    to: Option<Position>,
+    /// step through the game recorded in this PGN file with the arrow keys
+    #[arg(long, conflicts_with_all = ["white_ai", "black_ai"])]
+    replay: Option<PathBuf>,
+    /// where the s key saves the game as PGN and the l key loads it from
+    /// [default: a new chess_YYYYMMDD_HHMMSS.pgn in ~/.chess, and the latest of those]
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// give each player a clock, as minutes and an optional increment in seconds, e.g. `5+3`
+    #[arg(long, conflicts_with = "replay")]
+    time: Option<TimeControl>,
+    /// give white a clock of its own, as for `--time`, instead of that or unlimited time
+    #[arg(long, conflicts_with = "replay")]
+    time_white: Option<TimeControl>,
+    /// give black a clock of its own, as for `--time`, instead of that or unlimited time
+    #[arg(long, conflicts_with = "replay")]
+    time_black: Option<TimeControl>,
+    /// the board's colors: classic, green, blue, high-contrast or colorblind
+    #[arg(long, default_value = "classic")]
+    theme: Theme,
+    /// how the AI picks among equally good moves, to have it make the same choices again
+    /// [default: random]
+    #[arg(long)]
+    seed: Option<u64>,
+    /// have the AI play from this opening book, in Polyglot's format if it ends in `.bin` and
+    /// otherwise as lines of moves such as `e2e4 e7e5 g1f3`, one opening to a line
+    /// [default: a dozen main lines]
+    #[arg(long, value_name = "FILE", conflicts_with = "no_book")]
+    book: Option<PathBuf>,
+    /// have the AI search for every move, without an opening book
+    #[arg(long)]
+    no_book: bool,
+}
+
+/// plays a single move, promoting to a queen if need be
+fn play(game: Game, from: Position, to: Position) -> ExitCode {
+    if !game
+        .legal_moves_from(from)
+        .is_some_and(|moves| moves.contains(&to))
+    {
+        eprintln!("ERROR: {from}{to} is not a legal move");
+        return ExitCode::FAILURE;
+    }
+    let promotion = game.is_promotion(from, to).then_some(PieceType::Queen);
+    let r#move = Move::new(from, to, promotion);
+    println!("{}", game.san(r#move));
+    let game = game.apply_move_unchecked(r#move);
+    println!("{}", game.to_fen());
+    ExitCode::SUCCESS
+}
+
+/// how many plies `--selfplay` plays before giving up on the game ending
+const SELFPLAY_PLY_LIMIT: usize = 500;
+
+/// has the AI play `game` out against itself, searching `white_depth` and `black_depth` plies
+/// once out of `book`, and picking among book moves and equally good moves by `seed`
+fn selfplay(
+    game: Game,
+    white_depth: usize,
+    black_depth: usize,
+    seed: u64,
+    book: Option<&PolyglotBook>,
+) -> ExitCode {
+    let mut pgn = Pgn {
+        start: game.clone(),
+        moves: Vec::new(),
+        outcome: None,
+    };
+    let mut game = game;
+    while game.status().is_none() && pgn.moves.len() < SELFPLAY_PLY_LIMIT {
+        let depth = match game.turn() {
+            PieceColor::White => white_depth,
+            PieceColor::Black => black_depth,
+        };
+        let seed = seed.wrapping_add(pgn.moves.len() as u64);
+        let r#move = book
+            .and_then(|book| book.probe(&game, Some(seed)))
+            .unwrap_or_else(|| {
+                let mut engine = Engine::new();
+                engine.set_seed(seed);
+                engine.choose(&game, depth)
+            });
+        println!("{}", game.san(r#move));
+        game = game.apply_move_unchecked(r#move);
+        pgn.moves.push(r#move);
+    }
+    match game.status() {
+        Some(outcome) => print!("Outcome: {outcome}"),
+        None => println!("Stopped after {SELFPLAY_PLY_LIMIT} plies"),
+    }
+    println!("{}", game.to_fen());
+    println!("{pgn}");
+    ExitCode::SUCCESS
+}
+
+/// prints `game.perft_split(depth)` and its total, and how long it took to stderr
+fn perft(game: Game, depth: usize) -> ExitCode {
+    let start = Instant::now();
+    let split = game.perft_split(depth);
+    let elapsed = start.elapsed();
+    for (r#move, count) in &split {
+        println!("{move}: {count}");
+    }
+    println!(
+        "Total: {}",
+        split.iter().map(|(_, count)| count).sum::<u64>()
+    );
+    eprintln!("{} ms", elapsed.as_millis());
+    ExitCode::SUCCESS
 }
 
 fn main() -> ExitCode {
-    let Args { ai } = Args::parse();
-    let mut tui = Tui::new(ai);
+    let Args {
+        white_ai,
+        black_ai,
+        delay_ms,
+        selfplay: self_play,
+        white_depth,
+        black_depth,
+        fen,
+        chess960,
+        perft: perft_depth,
+        uci,
+        announce_moves,
+        from,
+        to,
+        replay,
+        output,
+        time,
+        time_white,
+        time_black,
+        theme,
+        seed,
+        book,
+        no_book,
+    } = Args::parse();
+    let seed = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+    let game = match fen.as_deref().map(Game::from_fen) {
+        None => chess960.map_or_else(Game::new, Game::chess960),
+        Some(Ok(game)) => game,
+        Some(Err(e)) => {
+            eprintln!("ERROR: invalid FEN: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let (Some(from), Some(to)) = (from, to) {
+        return play(game, from, to);
+    }
+    if let Some(depth) = perft_depth {
+        return perft(game, depth);
+    }
+    let book = match book {
+        _ if no_book => None,
+        None => Some(PolyglotBook::mainlines()),
+        Some(path) => match PolyglotBook::from_file(&path) {
+            Ok(book) => Some(book),
+            Err(e) => {
+                eprintln!("ERROR: {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    if self_play {
+        return selfplay(game, white_depth, black_depth, seed, book.as_ref());
+    }
+    if uci {
+        return match chess::uci::run(std::io::stdin().lock(), std::io::stdout().lock()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("ERROR: {e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let mut tui = Tui::new(game, [white_ai, black_ai], announce_moves);
+    tui.set_ai_delay(Duration::from_millis(delay_ms));
+    if let Some(path) = output {
+        tui.set_output(path);
+    }
+    tui.set_theme(theme);
+    tui.set_seed(seed);
+    if let Some(book) = book {
+        tui.set_book(book);
+    }
+    tui.set_time_control([time_white.or(time), time_black.or(time)]);
+    if let Some(path) = replay {
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|pgn| tui.replay_mode(&pgn).map_err(|e| e.to_string()));
+        if let Err(e) = loaded {
+            eprintln!("ERROR: {}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
     let mut terminal = ratatui::init();
     terminal
         .backend_mut()
@@ -36,6 +303,9 @@ fn main() -> ExitCode {
             } else {
                 println!("Quit before game ended");
             }
+            if tui.hints_used() > 0 {
+                println!("Hints used: {}", tui.hints_used());
+            }
             ExitCode::SUCCESS
         }
         Err(e) => {
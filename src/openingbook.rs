@@ -0,0 +1,377 @@
+//! opening books in the Polyglot `.bin` format, which most chess GUIs and engines share, or
+//! written out as lines of moves
+
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    hash::{BuildHasher, Hasher},
+    io,
+    path::Path,
+};
+
+use crate::game::{splitmix64, Game, Move, PieceType, Position};
+
+/// a dozen main lines of well-known openings, as `PolyglotBook::from_text` reads them
+const MAINLINES: &str = "\
+# Ruy Lopez
+e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7
+# Italian Game
+e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d3 d7d6
+# Scotch Game
+e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4 g8f6 d4c6 b7c6
+# Petrov's Defence
+e2e4 e7e5 g1f3 g8f6 f3e5 d7d6 e5f3 f6e4 d2d4 d6d5
+# Sicilian Defence, Najdorf Variation
+e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6
+# French Defence, Classical Variation
+e2e4 e7e6 d2d4 d7d5 b1c3 g8f6 c1g5 f8e7 e4e5 f6d7
+# Caro-Kann Defence, Classical Variation
+e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5 e4g3 f5g6
+# Queen's Gambit Declined
+d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7 e2e3 e8g8
+# Slav Defence
+d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 d5c4 a2a4 c8f5
+# King's Indian Defence
+d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8
+# Nimzo-Indian Defence
+d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8g8 f1d3 d7d5
+# English Opening
+c2c4 e7e5 b1c3 g8f6 g1f3 b8c6 g2g3 d7d5 c4d5 f6d5
+# Réti Opening
+g1f3 d7d5 g2g3 g8f6 f1g2 e7e6 e1g1 f8e7 d2d3 e8g8
+";
+
+/// one book move: in the position with Polyglot key `key`, `r#move` with weight `weight`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PolyglotEntry {
+    pub key: u64,
+    /// from the lowest bits up: the destination file and rank, the origin file and rank, three
+    /// bits each, then the piece a pawn promotes to, from 1 for a knight to 4 for a queen, or 0.
+    /// Castling is written as the king moving onto its own rook.
+    pub r#move: u16,
+    /// how often to play the move relative to the others in the position
+    pub weight: u16,
+    /// unused here, but kept so that a book reads back as it was written
+    pub learn: u32,
+}
+
+/// bytes per entry: 8 for the key, 2 for the move, 2 for the weight and 4 for learning data,
+/// all big-endian
+const ENTRY_SIZE: usize = 16;
+
+impl PolyglotEntry {
+    fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> Self {
+        let (key, rest) = bytes.split_at(8);
+        let (r#move, rest) = rest.split_at(2);
+        let (weight, learn) = rest.split_at(2);
+        Self {
+            key: u64::from_be_bytes(key.try_into().expect("8 bytes")),
+            r#move: u16::from_be_bytes(r#move.try_into().expect("2 bytes")),
+            weight: u16::from_be_bytes(weight.try_into().expect("2 bytes")),
+            learn: u32::from_be_bytes(learn.try_into().expect("4 bytes")),
+        }
+    }
+
+    /// `r#move`'s bits, as the `r#move` field holds them
+    /// REQUIRES: `r#move` is legal in `game`
+    fn encode(game: &Game, r#move: Move) -> u16 {
+        let (from, mut to) = (r#move.from(), r#move.to());
+        let onto_rook = game
+            .get(to)
+            .is_some_and(|piece| piece.piece == PieceType::Rook);
+        if game.is_castling(from, to) && !onto_rook {
+            let file = if to.file() > from.file() { 7 } else { 0 };
+            to = Position::new(from.rank(), file);
+        }
+        let promotion = match r#move.promotion() {
+            None => 0,
+            Some(PieceType::Knight) => 1,
+            Some(PieceType::Bishop) => 2,
+            Some(PieceType::Rook) => 3,
+            Some(_) => 4,
+        };
+        let square = |position: Position| (position.rank() as u16) << 3 | position.file() as u16;
+        promotion << 12 | square(from) << 6 | square(to)
+    }
+
+    /// the entry's move in `game`, or None if it is not legal there, as it may not be if
+    /// another position shares the key
+    fn to_move(self, game: &Game) -> Option<Move> {
+        let square = |bits: u16| {
+            let file = (b'a' + (bits & 0b111) as u8) as char;
+            let rank = (b'1' + (bits >> 3 & 0b111) as u8) as char;
+            format!("{file}{rank}")
+        };
+        let promotion = match self.r#move >> 12 & 0b111 {
+            0 => "",
+            1 => "n",
+            2 => "b",
+            3 => "r",
+            4 => "q",
+            _ => return None,
+        };
+        // coordinate notation also writes castling as the king moving onto its rook
+        let uci = square(self.r#move >> 6) + &square(self.r#move) + promotion;
+        Move::from_uci_str(&uci, game).ok()
+    }
+}
+
+/// a Polyglot opening book: entries sorted by key, so that a position's moves are together
+pub struct PolyglotBook {
+    pub entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    /// reads a book in Polyglot's format if `path` ends in `.bin`, and as `from_text` otherwise
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        if path.extension().is_some_and(|extension| extension == "bin") {
+            Self::from_bytes(&std::fs::read(path)?)
+        } else {
+            Self::from_text(&std::fs::read_to_string(path)?)
+        }
+    }
+
+    /// a book of openings written one to a line as moves in coordinate notation from the
+    /// starting position, e.g. `e2e4 e7e5 g1f3`, ignoring anything after a `#`. Each move is
+    /// weighted by how many lines play it in its position, even if they reach it by different
+    /// move orders.
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let mut weights: BTreeMap<(u64, u16), u16> = BTreeMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut game = Game::new();
+            for word in line.split_whitespace() {
+                let r#move = Move::from_uci_str(word, &game).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: {e}", number + 1),
+                    )
+                })?;
+                let key = (game.polyglot_key(), PolyglotEntry::encode(&game, r#move));
+                let weight = weights.entry(key).or_default();
+                *weight = weight.saturating_add(1);
+                game = game.apply_move_unchecked(r#move);
+            }
+        }
+        let entries = weights
+            .into_iter()
+            .map(|((key, r#move), weight)| PolyglotEntry {
+                key,
+                r#move,
+                weight,
+                learn: 0,
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// a small book of a dozen main lines, for varying the AI's play in the opening
+    pub fn mainlines() -> Self {
+        Self::from_text(MAINLINES).expect("valid book")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let chunks = bytes.chunks_exact(ENTRY_SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("a Polyglot book is made of {ENTRY_SIZE}-byte entries"),
+            ));
+        }
+        let mut entries: Vec<PolyglotEntry> = chunks
+            .map(|chunk| PolyglotEntry::from_bytes(chunk.try_into().expect("exact chunks")))
+            .collect();
+        // books are written sorted, but a stable sort of a sorted list is cheap
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Self { entries })
+    }
+
+    /// the book moves for `game` that are legal in it, with their weights
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u16)> {
+        let key = game.polyglot_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| Some((entry.to_move(game)?, entry.weight)))
+            .collect()
+    }
+
+    /// a book move for `game`, chosen at random in proportion to the weights, or None if the
+    /// book has no move for it. Moves of weight 0 are never chosen. The choice is determined
+    /// by `seed`, if any.
+    pub fn probe(&self, game: &Game, seed: Option<u64>) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u64 = moves.iter().map(|&(_, weight)| weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let random = match seed {
+            Some(mut seed) => splitmix64(&mut seed),
+            None => RandomState::new().build_hasher().finish(),
+        };
+        let mut roll = random % total;
+        moves.into_iter().find_map(|(r#move, weight)| {
+            if roll < weight as u64 {
+                Some(r#move)
+            } else {
+                roll -= weight as u64;
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PolyglotBook, PolyglotEntry};
+    use crate::game::{square, Game, Move};
+
+    fn entry_bytes(key: u64, r#move: u16, weight: u16) -> Vec<u8> {
+        [
+            &key.to_be_bytes()[..],
+            &r#move.to_be_bytes(),
+            &weight.to_be_bytes(),
+            &0u32.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn polyglot_key() {
+        // from the Polyglot book format's description
+        let keys = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b96181691fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ];
+        for (fen, key) in keys {
+            let game = Game::from_fen(fen).expect("valid fen");
+            assert_eq!(game.polyglot_key(), key, "{fen}");
+        }
+    }
+
+    #[test]
+    fn probe() {
+        // e2e4 from the starting position
+        let bytes = entry_bytes(0x463b96181691fc9c, 0x031c, 1);
+        let book = PolyglotBook::from_bytes(&bytes).expect("whole entries");
+        assert_eq!(
+            book.entries,
+            vec![PolyglotEntry {
+                key: 0x463b96181691fc9c,
+                r#move: 0x031c,
+                weight: 1,
+                learn: 0,
+            }]
+        );
+        let e4 = Move::Normal(square("e2"), square("e4"));
+        assert_eq!(book.probe(&Game::new(), None), Some(e4));
+        let after = Game::new().apply(e4).expect("legal");
+        assert_eq!(book.probe(&after, None), None);
+        assert!(PolyglotBook::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn castling() {
+        // Polyglot writes O-O as e1h1
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").expect("valid fen");
+        let bytes = entry_bytes(game.polyglot_key(), 4 << 6 | 7, 1);
+        let book = PolyglotBook::from_bytes(&bytes).expect("whole entries");
+        assert_eq!(
+            book.probe(&game, None),
+            Some(Move::Normal(square("e1"), square("g1")))
+        );
+    }
+
+    #[test]
+    fn from_text() {
+        let book = PolyglotBook::from_text(
+            "e2e4 e7e5 # the open game\n\
+             e2e4 c7c5\n\
+             \n\
+             d2d4 d7d5\n\
+             e1g1",
+        );
+        assert!(book.is_err(), "castling is not legal from the start");
+
+        let book = PolyglotBook::from_text("e2e4 e7e5 # the open game\ne2e4 c7c5\n\nd2d4 d7d5")
+            .expect("legal lines");
+        let e4 = Move::Normal(square("e2"), square("e4"));
+        let d4 = Move::Normal(square("d2"), square("d4"));
+        let mut moves = book.moves(&Game::new());
+        moves.sort_by_key(|&(_, weight)| weight);
+        assert_eq!(moves, vec![(d4, 1), (e4, 2)]);
+        let after = Game::new().apply(e4).expect("legal");
+        assert_eq!(book.moves(&after).len(), 2);
+    }
+
+    #[test]
+    fn seeded_probe() {
+        let book = PolyglotBook::mainlines();
+        let game = Game::new();
+        for seed in 0..16 {
+            assert_eq!(book.probe(&game, Some(seed)), book.probe(&game, Some(seed)));
+        }
+        // the book has more than one first move, and other seeds choose other ones
+        let first = book.probe(&game, Some(0));
+        assert!((1..64).any(|seed| book.probe(&game, Some(seed)) != first));
+    }
+
+    #[test]
+    fn mainlines() {
+        let book = PolyglotBook::mainlines();
+        // every move in the book, castling included, decodes back to what it was written from
+        for line in super::MAINLINES
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+        {
+            let mut game = Game::new();
+            for word in line.split_whitespace() {
+                let r#move = Move::from_uci_str(word, &game).expect("legal");
+                let encoded = PolyglotEntry::encode(&game, r#move);
+                let entry = PolyglotEntry {
+                    key: game.polyglot_key(),
+                    r#move: encoded,
+                    weight: 1,
+                    learn: 0,
+                };
+                assert_eq!(entry.to_move(&game), Some(r#move), "{line}: {word}");
+                assert!(book.moves(&game).iter().any(|&(m, _)| m == r#move));
+                game = game.apply_move_unchecked(r#move);
+            }
+        }
+    }
+}
@@ -1,74 +1,458 @@
+mod announce;
+mod clock;
+mod theme;
+
+pub use clock::{ParseTimeControlError, TimeControl};
+pub use theme::{ParseThemeError, Theme};
+
 use ratatui::{
     crossterm::event::{
         self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
     },
     layout::Flex,
     prelude::*,
+    widgets::{List, ListState},
+};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use std::collections::BTreeMap;
 
+use self::clock::Clock;
 use crate::{
-    ai::{self, Move},
-    game::{Game, Outcome, PieceColor, PieceType, Position, PROMOTIONS},
+    ai::{self, Evaluation, Move},
+    game::{
+        DrawReason, Game, Movement, Outcome, Pgn, PgnError, Piece, PieceColor, PieceType, Position,
+        Targets, PROMOTIONS,
+    },
+    openingbook::PolyglotBook,
 };
 
+/// width of the info column, enough for e.g. `⠋ Thinking…` or `White 04:59.9`
+const INFO_WIDTH: u16 = 13;
+
+/// how often to redraw while waiting for input, to animate the spinner and the king in check
+const TICK: Duration = Duration::from_millis(100);
+
+/// ticks between the two shades of a king in check
+const PULSE_TICKS: usize = 5;
+
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// how many plies deep to search for a hint when there is no AI opponent to take the depth from
+const HINT_DEPTH: usize = 4;
+
+/// how long the AI may think about a draw offer, during which the TUI does not respond
+const DRAW_OFFER_BUDGET: Duration = Duration::from_millis(500);
+
+/// how long to show where the game was saved
+const SAVED_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+/// how many moves of the line the AI expects to show after it plays
+const PV_SHOWN: usize = 5;
+
+/// plies without a capture or a pawn move after which the game is drawn
+const FIFTY_MOVES: u32 = 2 * 50;
+
+/// how many plies deep to search to judge a human's move for the evaluation bar, shallow
+/// enough to keep up with the game
+const EVAL_BAR_DEPTH: usize = 2;
+
+/// centipawns at which the evaluation bar is all one color
+const EVAL_BAR_RANGE: i32 = 500;
+
+/// rows in the evaluation bar, alongside the board
+const EVAL_BAR_CELLS: u16 = 8;
+
+/// width of the move history panel, enough for e.g. `100. exd8=Q+ Qxd8#`
+const HISTORY_WIDTH: u16 = 5 + 7 + 1 + 7;
+
+/// a half-move that has been played
+struct Ply {
+    /// in standard algebraic notation
+    san: String,
+    r#move: Move,
+    captured: Option<Piece>,
+    /// the game before the half-move was played, to jump back to
+    before: Game,
+    /// the AI's judgement of the game after the half-move, for the evaluation bar, if it has
+    /// made one
+    evaluation: Option<Evaluation>,
+    /// the judgement of a human's half-move, on its way from another thread
+    evaluating: Option<Receiver<Chosen>>,
+}
+
+/// a yes-or-no question awaiting an answer
+#[derive(Clone, Copy)]
+enum Prompt {
+    /// whether the player to move accepts their opponent's offer of a draw
+    AcceptDraw,
+    /// whether the given color, played by a human, means to resign
+    Resign(PieceColor),
+    /// whether to abandon the game in progress for a new one
+    NewGame,
+}
+
+impl Prompt {
+    fn question(self) -> &'static str {
+        match self {
+            Prompt::AcceptDraw => "Accept draw? y/n",
+            Prompt::Resign(_) => "Resign? y/n",
+            Prompt::NewGame => "New game? y/n",
+        }
+    }
+}
+
+/// a move the AI chose, with the line it expects to follow and its evaluation, which a move
+/// from the opening book has none of
+type Chosen = (Move, Vec<Move>, Option<Evaluation>);
+
 pub struct Tui {
     game: Game,
+    /// where the game started, and a new game starts again
+    start: Game,
     click_targets: Vec<(Rect, Position)>,
-    selected_tile: Option<(Position, Vec<Position>)>,
+    selected_tile: Option<(Position, Targets)>,
     selected_promotion: Option<(
         Position,
         Position,
         BTreeMap<ratatui::layout::Position, PieceType>,
     )>,
-    last_move: Option<(Position, Position)>,
-    ai: Option<usize>,
+    history: Vec<Ply>,
+    history_click_targets: Vec<(Rect, usize)>,
+    /// how many plies deep the AI searches for each color it plays, indexed by color
+    ai: [Option<usize>; 2],
+    /// the least time between the AI starting to think and playing its move, so that a game
+    /// between AIs can be watched
+    ai_delay: Duration,
+    /// whether to speak each move aloud
+    announce_moves: bool,
+    /// square highlighted for keyboard input
+    cursor: Position,
+    /// index into `PROMOTIONS` highlighted for keyboard input
+    promotion_cursor: usize,
+    /// whether black is at the bottom of the screen
+    flipped: bool,
+    /// whether to label the ranks and files
+    coordinates: bool,
+    /// receives the AI's move, and the line it expects to follow, while it is thinking on
+    /// another thread, with no evaluation for a move from `book`
+    thinking: Option<Receiver<Chosen>>,
+    /// the move the AI suggests to the human, after pressing `h`
+    hint: Option<Move>,
+    /// receives the suggested move while the AI works it out on another thread
+    hinting: Option<Receiver<Chosen>>,
+    /// how many hints the human has asked for
+    hints_used: u32,
+    /// redraws without input, for animation
+    ticks: usize,
+    /// every move of a recorded game being stepped through, of which `history` holds those
+    /// played so far
+    replay: Option<Vec<Move>>,
+    /// how the game ended, if not on the board, e.g. by resignation
+    outcome: Option<Outcome>,
+    /// shown instead of the help line until answered
+    prompt: Option<Prompt>,
+    /// shown instead of the help line until the next key press
+    message: Option<String>,
+    /// when to stop showing `message` if no key is pressed before
+    message_expires: Option<Instant>,
+    /// where `s` saves the game and `l` loads it from, if not the latest save in `save_dir`
+    output: Option<PathBuf>,
+    /// where `s` saves the game in a new file named for the time
+    save_dir: PathBuf,
+    /// how much time each player gets, indexed by color, None for unlimited time
+    time_control: [Option<TimeControl>; 2],
+    /// counts down the time of the player to move, if the game is timed and not being replayed
+    clock: Option<Clock>,
+    theme: Theme,
+    /// picks among the AI's equally good moves if set, see `ply_seed`
+    seed: Option<u64>,
+    /// where the AI looks for its move before searching, if anywhere
+    book: Option<Arc<PolyglotBook>>,
 }
 
 impl Tui {
-    pub fn new(ai: Option<usize>) -> Self {
-        let game = Game::new();
+    /// `ai` is how many plies deep the AI searches for each color it plays, indexed by color
+    pub fn new(game: Game, ai: [Option<usize>; 2], announce_moves: bool) -> Self {
+        let start = game.clone();
         let click_targets = Vec::new();
         let selected_tile = None;
         let selected_promotion = None;
-        let last_move = None;
+        let history = Vec::new();
+        let history_click_targets = Vec::new();
+        let cursor = Position::new(1, 4);
+        let promotion_cursor = 0;
+        // the human sits at the bottom of the screen
+        let flipped = matches!(ai, [Some(_), None]);
+        let ai_delay = Duration::ZERO;
+        let coordinates = true;
+        let thinking = None;
+        let hint = None;
+        let hinting = None;
+        let hints_used = 0;
+        let ticks = 0;
+        let replay = None;
+        let outcome = None;
+        let prompt = None;
+        let message = None;
+        let message_expires = None;
+        let output = None;
+        let save_dir = default_save_dir();
+        let time_control = [None; 2];
+        let clock = None;
+        let theme = Theme::default();
+        let seed = None;
+        let book = None;
         Self {
             game,
+            start,
             click_targets,
             selected_tile,
             selected_promotion,
-            last_move,
+            history,
+            history_click_targets,
             ai,
+            ai_delay,
+            announce_moves,
+            cursor,
+            promotion_cursor,
+            flipped,
+            coordinates,
+            thinking,
+            hint,
+            hinting,
+            hints_used,
+            ticks,
+            replay,
+            outcome,
+            prompt,
+            message,
+            message_expires,
+            output,
+            save_dir,
+            time_control,
+            clock,
+            theme,
+            seed,
+            book,
         }
     }
 
+    pub fn set_output(&mut self, path: PathBuf) {
+        self.output = Some(path);
+    }
+
+    /// gives each player a clock, starting now, and ends the game when either runs out.
+    /// `time_control` is indexed by color, and a player given None has unlimited time.
+    pub fn set_time_control(&mut self, time_control: [Option<TimeControl>; 2]) {
+        self.time_control = time_control;
+        self.clock = self.new_clock();
+    }
+
+    /// a clock for `time_control` starting now, if either player is timed
+    fn new_clock(&self) -> Option<Clock> {
+        self.time_control
+            .iter()
+            .any(Option::is_some)
+            .then(|| Clock::new(self.time_control, Instant::now()))
+    }
+
+    pub fn set_ai_delay(&mut self, delay: Duration) {
+        self.ai_delay = delay;
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// has the AI pick at random among equally good moves, in a way `seed` determines
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// has the AI play from `book` while it has a move for the position
+    pub fn set_book(&mut self, book: PolyglotBook) {
+        self.book = Some(Arc::new(book));
+    }
+
+    /// the seed for a search at this point in the game, which differs from ply to ply so
+    /// that ties are not always broken alike
+    fn ply_seed(&self) -> Option<u64> {
+        self.seed
+            .map(|seed| seed.wrapping_add(self.history.len() as u64))
+    }
+
+    pub fn hints_used(&self) -> u32 {
+        self.hints_used
+    }
+
+    /// sets up the game recorded in `pgn` to be stepped through with the arrow keys, instead of
+    /// played
+    pub fn replay_mode(&mut self, pgn: &str) -> Result<(), PgnError> {
+        let Pgn { start, moves, .. } = pgn.parse()?;
+        self.game = start;
+        self.history.clear();
+        self.selected_tile = None;
+        self.selected_promotion = None;
+        self.thinking = None;
+        self.clock = None;
+        self.replay = Some(moves);
+        Ok(())
+    }
+
     pub fn run<B: Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
     ) -> std::io::Result<Option<Outcome>> {
         let outcome = loop {
+            if self.outcome().is_none() {
+                self.tick_clock(Instant::now());
+            }
+            self.expire_message(Instant::now());
             terminal.draw(|frame| frame.render_widget(&mut *self, frame.area()))?;
-            if let Some(outcome) = self.game.status() {
-                while !matches!(event::read()?, Event::Key(_)) {}
-                break Some(outcome);
+            if let Some(outcome) = self.outcome() {
+                if self.handle_game_over(event::read()?) {
+                    break Some(outcome);
+                }
+                continue;
+            }
+            if let (Some(depth), None, None) = (
+                self.ai[self.game.turn() as usize],
+                &self.thinking,
+                &self.replay,
+            ) {
+                self.thinking = Some(search(
+                    &self.game,
+                    depth,
+                    self.ai_delay,
+                    self.ply_seed(),
+                    self.book.clone(),
+                ));
             }
-            if let (Some(ai), PieceColor::Black) = (self.ai, self.game.turn()) {
-                match ai::choose(&self.game, ai) {
-                    Move::Move(from, to) => self.r#move(from, to),
-                    Move::Promote(from, to, piece_type) => self.promote(from, to, piece_type),
+            if let Some(receiver) = &self.thinking {
+                match receiver.try_recv() {
+                    Ok((r#move, pv, evaluation)) => {
+                        self.thinking = None;
+                        self.message = Some(match evaluation {
+                            Some(_) => engine_line(&pv),
+                            None => format!("Book: {move}"),
+                        });
+                        self.play_evaluated(r#move, evaluation);
+                        continue;
+                    }
+                    Err(TryRecvError::Empty) => {}
+                    Err(TryRecvError::Disconnected) => panic!("AI thread panicked"),
                 }
-            } else {
-                let event = event::read()?;
-                if self.handle(event) {
+            }
+            self.receive_evaluations();
+            if let Some(receiver) = &self.hinting {
+                match receiver.try_recv() {
+                    Ok((r#move, _, _)) => {
+                        self.hinting = None;
+                        self.hint = Some(r#move);
+                        continue;
+                    }
+                    Err(TryRecvError::Empty) => {}
+                    Err(TryRecvError::Disconnected) => panic!("hint thread panicked"),
+                }
+            }
+            if event::poll(TICK)? {
+                if self.handle(event::read()?) {
                     break None;
                 }
+            } else {
+                self.ticks += 1;
             }
         };
-        return Ok(outcome);
+        Ok(outcome)
+    }
+
+    /// how the game ended, if it has
+    fn outcome(&self) -> Option<Outcome> {
+        if self.replay.is_some() {
+            return None;
+        }
+        self.outcome
+            .or_else(|| self.game.status())
+            .or_else(|| self.draw_by_history().map(Outcome::Draw))
+    }
+
+    /// a draw called for by the moves played, which the game alone does not know: the same
+    /// position for the third time, or fifty moves by each side without a capture or a pawn
+    /// move, counting those before the game started from a FEN
+    fn draw_by_history(&self) -> Option<DrawReason> {
+        let repetitions = self
+            .history
+            .iter()
+            .filter(|ply| ply.before == self.game)
+            .count();
+        if repetitions >= 2 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.game.halfmove_clock() >= FIFTY_MOVES {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// counts down the time of the player to move, who loses if it runs out
+    fn tick_clock(&mut self, now: Instant) {
+        let turn = self.game.turn();
+        if let Some(clock) = &mut self.clock {
+            if clock.tick(turn, now) {
+                self.thinking = None;
+                self.outcome = Some(Outcome::Timeout(turn));
+            }
+        }
+    }
+
+    /// handles `event` once the game is over, starting a new one on n.
+    /// Returns whether to exit.
+    fn handle_game_over(&mut self, event: Event) -> bool {
+        match event {
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('n'),
+                kind: KeyEventKind::Press,
+                ..
+            }) => {
+                self.new_game();
+                false
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Esc | KeyCode::Char('q'),
+                kind: KeyEventKind::Press,
+                ..
+            }) => true,
+            _ => false,
+        }
     }
 
     /// returns whether to exit
     fn handle(&mut self, event: Event) -> bool {
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event
+        {
+            self.message = None;
+            self.message_expires = None;
+            if let Some(prompt) = self.prompt.take() {
+                if code == KeyCode::Char('y') {
+                    self.answer(prompt);
+                }
+                return false;
+            }
+        }
         match event {
             Event::Key(KeyEvent {
                 code: KeyCode::Esc | KeyCode::Char('q'),
@@ -76,6 +460,58 @@ impl Tui {
                 modifiers: _,
                 state: _,
             }) => true,
+            Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                modifiers: _,
+                state: _,
+            }) => {
+                // directions as seen on screen
+                type Direction = fn(Position) -> Option<Position>;
+                let (up, left, down, right): (Direction, Direction, Direction, Direction) =
+                    if self.flipped {
+                        (
+                            Movement::down,
+                            Movement::right,
+                            Movement::up,
+                            Movement::left,
+                        )
+                    } else {
+                        (
+                            Movement::up,
+                            Movement::left,
+                            Movement::down,
+                            Movement::right,
+                        )
+                    };
+                match code {
+                    KeyCode::Left if self.replay.is_some() => self.step_back(),
+                    KeyCode::Right if self.replay.is_some() => self.step_forward(),
+                    KeyCode::Up => self.move_cursor(up, -1),
+                    KeyCode::Left => self.move_cursor(left, -1),
+                    KeyCode::Down => self.move_cursor(down, 1),
+                    KeyCode::Right => self.move_cursor(right, 1),
+                    KeyCode::Char('f') => self.flipped = !self.flipped,
+                    KeyCode::Char('c') => self.coordinates = !self.coordinates,
+                    KeyCode::Char('h') => self.request_hint(),
+                    KeyCode::Char('u') => self.undo(),
+                    KeyCode::Char('n') => self.confirm_new_game(),
+                    KeyCode::Char('r') => self.resign(),
+                    KeyCode::Char('d') => self.offer_draw(),
+                    KeyCode::Char('s') => self.save(),
+                    KeyCode::Char('l') => self.load(),
+                    KeyCode::Enter | KeyCode::Char(' ') => {
+                        if let Some((from, to, _)) = self.selected_promotion {
+                            self.play(Move::Promote(from, to, PROMOTIONS[self.promotion_cursor]));
+                            self.selected_promotion = None;
+                        } else {
+                            self.activate(self.cursor);
+                        }
+                    }
+                    _ => {}
+                }
+                false
+            }
             Event::Mouse(MouseEvent {
                 kind: MouseEventKind::Down(MouseButton::Left),
                 column,
@@ -85,32 +521,23 @@ impl Tui {
                 let click = ratatui::layout::Position { x: column, y: row };
                 if let Some((from, to, ref click_targets)) = self.selected_promotion {
                     if let Some(&piece_type) = click_targets.get(&click) {
-                        self.promote(from, to, piece_type);
+                        self.play(Move::Promote(from, to, piece_type));
                         self.selected_promotion = None;
                         return false;
                     }
                 }
-                for &(rect, position) in &self.click_targets {
-                    if rect.contains(click) {
-                        match self.selected_tile {
-                            None => self.select_tile(position),
-                            Some((from, ref moves)) => {
-                                if moves.contains(&position) {
-                                    if self.game.is_promotion(from, position) {
-                                        // promotion click targets will be populated upon rendering
-                                        self.selected_promotion =
-                                            Some((from, position, BTreeMap::new()));
-                                    } else {
-                                        self.r#move(from, position);
-                                    }
-                                    self.selected_tile = None;
-                                } else {
-                                    self.select_tile(position);
-                                }
-                            }
-                        }
-                        return false;
-                    }
+                if let Some(&(_, position)) = self
+                    .click_targets
+                    .iter()
+                    .find(|(rect, _)| rect.contains(click))
+                {
+                    self.activate(position);
+                } else if let Some(&(_, ply)) = self
+                    .history_click_targets
+                    .iter()
+                    .find(|(rect, _)| rect.contains(click))
+                {
+                    self.jump(ply);
                 }
                 false
             }
@@ -118,29 +545,443 @@ impl Tui {
         }
     }
 
-    fn r#move(&mut self, from: Position, to: Position) {
-        self.game = self.game.r#move(from, to);
-        self.last_move = Some((from, to));
+    /// moves the board cursor, or cycles the promotion cursor by `step` while promoting
+    fn move_cursor(&mut self, direction: fn(Position) -> Option<Position>, step: isize) {
+        if self.selected_promotion.is_some() {
+            self.promotion_cursor = (self.promotion_cursor as isize + step)
+                .rem_euclid(PROMOTIONS.len() as isize) as usize;
+        } else if let Some(cursor) = direction(self.cursor) {
+            self.cursor = cursor;
+        }
+    }
+
+    /// the square shown `row` rows down and `column` columns across from the top left
+    fn position_at(&self, row: usize, column: usize) -> Position {
+        let (row, column) = (row as u8, column as u8);
+        if self.flipped {
+            Position::new(row, 7 - column)
+        } else {
+            Position::new(7 - row, column)
+        }
+    }
+
+    /// responds to the user choosing `position`, by click or by keyboard
+    fn activate(&mut self, position: Position) {
+        if self.thinking.is_some() || self.replay.is_some() {
+            return;
+        }
+        match self.selected_tile {
+            Some((from, _)) if from == position => self.selected_tile = None,
+            Some((from, ref moves)) if moves.contains(&position) => {
+                if self.game.is_promotion(from, position) {
+                    // promotion click targets will be populated upon rendering
+                    self.selected_promotion = Some((from, position, BTreeMap::new()));
+                    self.promotion_cursor = 0;
+                } else {
+                    self.play(Move::Normal(from, position));
+                }
+                self.selected_tile = None;
+            }
+            _ => self.select_tile(position),
+        }
+    }
+
+    /// starts the AI working out a move for the human, to highlight once it is done
+    fn request_hint(&mut self) {
+        if self.thinking.is_some()
+            || self.hinting.is_some()
+            || self.replay.is_some()
+            || self.game.status().is_some()
+        {
+            return;
+        }
+        let depth = self.ai[!self.game.turn() as usize].unwrap_or(HINT_DEPTH);
+        self.hinting = Some(search(
+            &self.game,
+            depth,
+            Duration::ZERO,
+            self.ply_seed(),
+            None,
+        ));
+        self.hints_used += 1;
+    }
+
+    /// forgets any hint, which only applies to the game it was asked for in
+    fn clear_hint(&mut self) {
+        self.hint = None;
+        self.hinting = None;
+    }
+
+    /// plays a human's move, judging it with a quick search on another thread
+    /// REQUIRES: `r#move` is legal
+    fn play(&mut self, r#move: Move) {
+        self.play_evaluated(r#move, None);
+        if self.game.status().is_none() {
+            let evaluating = search(&self.game, EVAL_BAR_DEPTH, Duration::ZERO, None, None);
+            self.history.last_mut().expect("just played").evaluating = Some(evaluating);
+        }
+    }
+
+    /// plays `r#move` with the evaluation of the game after it if the AI has made one, and
+    /// otherwise leaves it unjudged unless it ends the game, as when replaying a game
+    /// REQUIRES: `r#move` is legal
+    fn play_evaluated(&mut self, r#move: Move, evaluation: Option<Evaluation>) {
+        let (from, to) = (r#move.from(), r#move.to());
+        let san = self.game.san(r#move);
+        if self.announce_moves {
+            announce::speak(&announce::announcement(&self.game, r#move));
+        }
+        let captured = self.game.is_capture(from, to).then(|| {
+            // en passant captures a pawn on a vacant square
+            self.game.get(to).unwrap_or(Piece {
+                piece: PieceType::Pawn,
+                color: !self.game.turn(),
+            })
+        });
+        let game = self.game.apply_move_unchecked(r#move);
+        if let Some(clock) = &mut self.clock {
+            clock.moved(self.game.turn(), Instant::now());
+        }
+        let before = std::mem::replace(&mut self.game, game);
+        self.clear_hint();
+        let evaluation = evaluation.or_else(|| self.game.status().map(Evaluation::Outcome));
+        self.history.push(Ply {
+            san,
+            r#move,
+            captured,
+            before,
+            evaluation,
+            evaluating: None,
+        });
+    }
+
+    /// records the judgements of human moves that have arrived from other threads
+    fn receive_evaluations(&mut self) {
+        for ply in &mut self.history {
+            if let Some(receiver) = &ply.evaluating {
+                match receiver.try_recv() {
+                    Ok((_, _, evaluation)) => {
+                        ply.evaluating = None;
+                        ply.evaluation = evaluation;
+                    }
+                    Err(TryRecvError::Empty) => {}
+                    Err(TryRecvError::Disconnected) => panic!("evaluation thread panicked"),
+                }
+            }
+        }
+    }
+
+    /// plays the next move of the game being replayed, if any
+    fn step_forward(&mut self) {
+        let next = self
+            .replay
+            .as_ref()
+            .and_then(|moves| moves.get(self.history.len()).copied());
+        if let Some(r#move) = next {
+            self.play_evaluated(r#move, None);
+        }
+    }
+
+    /// takes back the last move of the game being replayed, if any
+    fn step_back(&mut self) {
+        if let Some(ply) = self.history.pop() {
+            self.game = ply.before;
+            self.clear_hint();
+        }
+    }
+
+    /// returns the game to just after half-move `ply`, forgetting the half-moves since
+    fn jump(&mut self, ply: usize) {
+        if self.thinking.is_some() {
+            return;
+        }
+        let next = self.history.drain(ply + 1..).next();
+        if let Some(next) = next {
+            self.game = next.before;
+            self.selected_tile = None;
+            self.selected_promotion = None;
+            self.clear_hint();
+        }
+    }
+
+    /// takes back the human's last move, along with the AI's reply to it if any, so that the
+    /// human is on move again
+    fn undo(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        let human = |game: &Game| self.ai[game.turn() as usize].is_none();
+        if let Some(ply) = self.history.iter().rposition(|ply| human(&ply.before)) {
+            // a reply the AI is still thinking about is abandoned
+            self.thinking = None;
+            self.game = self.history.drain(ply..).next().expect("rposition").before;
+            self.selected_tile = None;
+            self.selected_promotion = None;
+            self.clear_hint();
+        }
+    }
+
+    /// the color a human plays, or the one to move if both are human, or None if the AI plays
+    /// both
+    fn human(&self) -> Option<PieceColor> {
+        let turn = self.game.turn();
+        [turn, !turn]
+            .into_iter()
+            .find(|&color| self.ai[color as usize].is_none())
+    }
+
+    /// asks the human, or the player to move if both are human, to confirm resigning
+    fn resign(&mut self) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(color) = self.human() {
+            self.prompt = Some(Prompt::Resign(color));
+        }
+    }
+
+    /// offers the opponent a draw, which the AI decides on for itself and a human sharing the
+    /// keyboard is asked to accept
+    fn offer_draw(&mut self) {
+        if self.thinking.is_some() || self.replay.is_some() {
+            return;
+        }
+        let Some(human) = self.human() else {
+            return;
+        };
+        if self.ai[!human as usize].is_none() {
+            self.prompt = Some(Prompt::AcceptDraw);
+        } else if ai::accepts_draw(&self.game, !human, DRAW_OFFER_BUDGET) {
+            self.outcome = Some(Outcome::Draw(DrawReason::Agreed));
+        } else {
+            self.message = Some("Draw declined".to_string());
+        }
+    }
+
+    /// the game so far, as far as it has been replayed if it is being
+    fn pgn(&self) -> Pgn {
+        Pgn {
+            start: self
+                .history
+                .first()
+                .map_or(&self.game, |ply| &ply.before)
+                .clone(),
+            moves: self.history.iter().map(|ply| ply.r#move).collect(),
+            outcome: self.outcome(),
+        }
+    }
+
+    /// writes the game so far as PGN to `output`, or else to a new file in `save_dir` named for
+    /// the time
+    fn save(&mut self) {
+        let path = self.output.clone().unwrap_or_else(|| {
+            self.save_dir
+                .join(format!("chess_{}.pgn", timestamp(SystemTime::now())))
+        });
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&path, self.pgn().to_string() + "\n"));
+        match saved {
+            Ok(()) => {
+                self.message = Some(format!("Saved to {}", path.display()));
+                self.message_expires = Some(Instant::now() + SAVED_MESSAGE_DURATION);
+            }
+            Err(e) => self.message = Some(format!("Could not save to {}: {e}", path.display())),
+        }
+    }
+
+    /// stops showing the message if it has been shown long enough by `now`
+    fn expire_message(&mut self, now: Instant) {
+        if self.message_expires.is_some_and(|expires| now >= expires) {
+            self.message = None;
+            self.message_expires = None;
+        }
+    }
+
+    /// replaces the game with the one saved at `output`, or else the latest saved in
+    /// `save_dir`, replaying its moves
+    fn load(&mut self) {
+        let path = match &self.output {
+            Some(path) => Ok(path.clone()),
+            None => match latest_save(&self.save_dir) {
+                Ok(Some(path)) => Ok(path),
+                Ok(None) => Err(format!("No saved game in {}", self.save_dir.display())),
+                Err(e) => Err(format!(
+                    "Could not load from {}: {e}",
+                    self.save_dir.display()
+                )),
+            },
+        };
+        let pgn = path.and_then(|path| match fs::read_to_string(&path) {
+            Ok(pgn) => match pgn.parse::<Pgn>() {
+                Ok(pgn) => Ok((path, pgn)),
+                Err(e) => Err(format!("Invalid saved game: {e}")),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(format!("No saved game at {}", path.display()))
+            }
+            Err(e) => Err(format!("Could not load {}: {e}", path.display())),
+        });
+        match pgn {
+            Ok((path, Pgn { start, moves, .. })) => {
+                self.new_game();
+                self.game = start;
+                // replaying the moves should not speak them or run the clock
+                let announce_moves = std::mem::replace(&mut self.announce_moves, false);
+                let clock = self.clock.take();
+                for r#move in moves {
+                    self.play_evaluated(r#move, None);
+                }
+                self.announce_moves = announce_moves;
+                self.clock = clock;
+                self.message = Some(format!("Loaded {}", path.display()));
+            }
+            Err(message) => self.message = Some(message),
+        }
+    }
+
+    /// acts on a yes to `prompt`
+    fn answer(&mut self, prompt: Prompt) {
+        match prompt {
+            Prompt::AcceptDraw => self.outcome = Some(Outcome::Draw(DrawReason::Agreed)),
+            Prompt::Resign(color) => {
+                self.thinking = None;
+                self.outcome = Some(Outcome::Resignation(color));
+            }
+            Prompt::NewGame => self.new_game(),
+        }
+    }
+
+    /// starts a new game, first asking to confirm if that would abandon a game in progress
+    fn confirm_new_game(&mut self) {
+        if self.history.is_empty() || self.outcome().is_some() || self.replay.is_some() {
+            self.new_game();
+        } else {
+            self.prompt = Some(Prompt::NewGame);
+        }
     }
 
-    fn promote(&mut self, from: Position, to: Position, piece_type: PieceType) {
-        self.game = self.game.promote(from, to, piece_type);
-        self.last_move = Some((from, to));
+    /// starts over from where the game started, abandoning any search and leaving replay mode
+    fn new_game(&mut self) {
+        self.game = self.start.clone();
+        self.history.clear();
+        self.selected_tile = None;
+        self.selected_promotion = None;
+        self.thinking = None;
+        self.clear_hint();
+        self.replay = None;
+        self.outcome = None;
+        self.prompt = None;
+        self.clock = self.new_clock();
     }
 
     fn select_tile(&mut self, position: Position) {
-        self.selected_tile = self.game.moves().find(|(p, _)| *p == position);
+        self.selected_tile = self
+            .game
+            .legal_moves_from(position)
+            .map(|moves| (position, moves));
         self.selected_promotion = None;
     }
 }
 
-fn highlight_last_move<'a>(line: Line<'a>) -> Line<'a> {
-    let bg = match line.style.bg {
-        Some(Color::DarkGray) => Color::Yellow,
-        Some(Color::Gray) => Color::LightYellow,
-        color => panic!("unexpected background color {color:?}"),
+/// `~/.chess`, or the working directory if there is no home directory
+fn default_save_dir() -> PathBuf {
+    std::env::var_os("HOME").map_or(PathBuf::from("."), |home| Path::new(&home).join(".chess"))
+}
+
+/// the file in `dir` that `Tui::save` wrote last, going by the times in their names
+fn latest_save(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut latest = None;
+    for entry in entries {
+        let name = entry?.file_name();
+        let is_save = name
+            .to_str()
+            .is_some_and(|name| name.starts_with("chess_") && name.ends_with(".pgn"));
+        if is_save && latest.as_ref().is_none_or(|latest| name > *latest) {
+            latest = Some(name);
+        }
+    }
+    Ok(latest.map(|name| dir.join(name)))
+}
+
+/// `time` in UTC as `YYYYMMDD_HHMMSS`, which sorts in time order
+fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    // the date from the day number, by Howard Hinnant's `civil_from_days`, counting in 400-year
+    // eras of 146097 days from 0000-03-01 so that leap days fall at the end of each year
+    let days = days + 719468;
+    let (era, day_of_era) = (days / 146097, days % 146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = (month_from_march + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}_{:02}{:02}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// has the AI choose a move on another thread, taking at least `delay`, and sends it with the
+/// principal variation starting with it and its evaluation, or just the move if it is from
+/// `book`
+fn search(
+    game: &Game,
+    depth: usize,
+    delay: Duration,
+    seed: Option<u64>,
+    book: Option<Arc<PolyglotBook>>,
+) -> Receiver<Chosen> {
+    let (sender, receiver) = mpsc::channel();
+    let game = game.clone();
+    let start = Instant::now();
+    thread::spawn(move || {
+        let chosen = match book.and_then(|book| book.probe(&game, seed)) {
+            Some(r#move) => (r#move, vec![r#move], None),
+            None => {
+                let (r#move, pv, evaluation) = ai::choose_with_pv(&game, depth, seed);
+                (r#move, pv, Some(evaluation))
+            }
+        };
+        thread::sleep(delay.saturating_sub(start.elapsed()));
+        sender.send(chosen)
+    });
+    receiver
+}
+
+/// how many of the evaluation bar's cells are white's, half of them for an even game, all of
+/// them once white is `EVAL_BAR_RANGE` centipawns ahead or has won, and none once black has
+fn white_cells(evaluation: Evaluation) -> u16 {
+    let centipawns = match evaluation {
+        Evaluation::Estimate(centipawns) => centipawns.clamp(-EVAL_BAR_RANGE, EVAL_BAR_RANGE),
+        Evaluation::Outcome(Outcome::Draw(_)) => 0,
+        // any other outcome is decisive, and beyond every estimate
+        decisive if decisive > Evaluation::Estimate(0) => EVAL_BAR_RANGE,
+        _ => -EVAL_BAR_RANGE,
     };
-    line.bg(bg)
+    let cells = EVAL_BAR_CELLS as i32;
+    // rounded to the nearest cell
+    ((2 * (centipawns + EVAL_BAR_RANGE) * cells + 2 * EVAL_BAR_RANGE) / (4 * EVAL_BAR_RANGE)) as u16
+}
+
+/// the first few moves of the line the AI expects, e.g. `Engine: e2e4 e7e5 g1f3…`
+fn engine_line(pv: &[Move]) -> String {
+    let moves: Vec<String> = pv.iter().take(PV_SHOWN).map(Move::to_string).collect();
+    let more = if pv.len() > PV_SHOWN { "…" } else { "" };
+    format!("Engine: {}{more}", moves.join(" "))
 }
 
 impl Widget for &mut Tui {
@@ -148,25 +989,72 @@ impl Widget for &mut Tui {
     where
         Self: Sized,
     {
-        let [area] = Layout::vertical([8]).flex(Flex::Center).areas(area);
-        let [board_area, info_area] = Layout::horizontal([8 * 2, 5])
+        let [area, help_area] = Layout::vertical([1 + 8 + 1 + 1, 2])
             .spacing(1)
             .flex(Flex::Center)
             .areas(area);
+        let [area, eval_bar_area, info_area, history_area] =
+            Layout::horizontal([1 + 8 * 2, 1, INFO_WIDTH, HISTORY_WIDTH])
+                .spacing(1)
+                .flex(Flex::Center)
+                .areas(area);
+        let [black_captures_area, area, file_labels_area, white_captures_area] =
+            Layout::vertical([1, 8, 1, 1]).areas(area);
+        let [rank_labels_area, board_area] = Layout::horizontal([1, 8 * 2]).areas(area);
+        let [_, file_labels_area] = Layout::horizontal([1, 8 * 2]).areas(file_labels_area);
+        let [_, eval_bar_area, _] = Layout::vertical([1, EVAL_BAR_CELLS, 2]).areas(eval_bar_area);
+        let [_, info_area, _] = Layout::vertical([1, 8, 2]).areas(info_area);
+        let [_, history_area, _] = Layout::vertical([1, 8, 2]).areas(history_area);
+
+        // coordinates, whose space stays reserved when hidden so the board does not move
+        if self.coordinates {
+            let rank_labels = Layout::vertical([Constraint::Fill(1); 8]).split(rank_labels_area);
+            for (row, rect) in rank_labels.iter().copied().enumerate() {
+                Span::raw((self.position_at(row, 0).rank() + 1).to_string())
+                    .fg(Color::DarkGray)
+                    .render(rect, buf);
+            }
+            let file_labels = Layout::horizontal([Constraint::Fill(1); 8]).split(file_labels_area);
+            for (column, rect) in file_labels.iter().copied().enumerate() {
+                Span::raw(char::from(b'a' + self.position_at(0, column).file()).to_string())
+                    .fg(Color::DarkGray)
+                    .render(rect, buf);
+            }
+        }
 
         // board
         let ranks = Layout::vertical([Constraint::Fill(1); 8]).split(board_area);
         self.click_targets.clear();
-        for (rank, rect) in ranks.iter().copied().rev().enumerate() {
+        for (row, rect) in ranks.iter().copied().enumerate() {
             let files = Layout::horizontal([Constraint::Fill(1); 8]).split(rect);
-            for (file, rect) in files.iter().copied().enumerate() {
-                let position = Position::new(rank as u8, file as u8);
-                let mut line = position.square();
+            for (column, rect) in files.iter().copied().enumerate() {
+                let position = self.position_at(row, column);
+                let mut line = position.square(&self.theme);
                 if self
-                    .last_move
-                    .is_some_and(|(from, to)| position == from || position == to)
+                    .history
+                    .last()
+                    .is_some_and(|ply| position == ply.r#move.from() || position == ply.r#move.to())
+                {
+                    line = line.bg(self.theme.last_move(position));
+                }
+                if let Some(hint) = self.hint {
+                    if position == hint.from() || position == hint.to() {
+                        line = line.bg(self.theme.hint);
+                    }
+                }
+                // the move that gave check never starts or ends on the king it checks, so this
+                // does not hide the last move's highlight
+                if self.game.get(position)
+                    == Some(Piece {
+                        piece: PieceType::King,
+                        color: self.game.turn(),
+                    })
+                    && self.game.check()
                 {
-                    line = highlight_last_move(line);
+                    line = line.bg(self.theme.check[self.ticks / PULSE_TICKS % 2]);
+                }
+                if position == self.cursor {
+                    line = line.bg(self.theme.cursor);
                 }
                 if let Some(piece) = self.game.get(position) {
                     line.push_span(piece.render())
@@ -182,22 +1070,74 @@ impl Widget for &mut Tui {
                         .as_ref()
                         .is_some_and(|(from, to, _)| *from == position || *to == position)
                 {
-                    line.push_span(Span::raw("●").fg(Color::LightYellow))
+                    line.push_span(Span::raw("●").fg(self.theme.selection))
                 }
                 if self
                     .selected_tile
                     .as_ref()
                     .is_some_and(|(_, moves)| moves.contains(&position))
                 {
-                    line.push_span(Span::raw("○").fg(Color::LightGreen))
+                    line.push_span(Span::raw("○").fg(self.theme.legal_move))
                 }
                 line.render(rect, buf);
                 self.click_targets.push((rect, position));
             }
         }
 
+        // captures, which may run on underneath the info column
+        let material = |color| -> i32 {
+            self.game
+                .iter(color)
+                .map(|(_, piece)| ai::value(piece.piece))
+                .sum()
+        };
+        let delta = material(PieceColor::White) - material(PieceColor::Black);
+        for (color, area) in [
+            (PieceColor::White, white_captures_area),
+            (PieceColor::Black, black_captures_area),
+        ] {
+            let mut captured: Vec<Piece> = self
+                .history
+                .iter()
+                .filter_map(|ply| ply.captured)
+                .filter(|piece| piece.color != color)
+                .collect();
+            captured.sort_by_key(|piece| Reverse(ai::value(piece.piece)));
+            let mut line: Line = captured
+                .into_iter()
+                .map(|piece| piece.render().bg(Color::Gray))
+                .collect();
+            let ahead = match color {
+                PieceColor::White => delta,
+                PieceColor::Black => -delta,
+            };
+            if ahead > 0 {
+                line.push_span(format!(" +{ahead}"));
+            }
+            let area = Rect {
+                width: area.width + 1 + eval_bar_area.width + 1 + info_area.width,
+                ..area
+            };
+            line.render(area, buf);
+        }
+
+        // evaluation bar, filling from white's side of the board with the latest judgement
+        if let Some(evaluation) = self.history.iter().rev().find_map(|ply| ply.evaluation) {
+            let white = white_cells(evaluation);
+            for (row, cell) in eval_bar_area.rows().enumerate() {
+                let from_bottom = EVAL_BAR_CELLS - 1 - row as u16;
+                let from_white = if self.flipped {
+                    row as u16
+                } else {
+                    from_bottom
+                };
+                let symbol = if from_white < white { "▓" } else { "░" };
+                Span::raw(symbol).render(cell, buf);
+            }
+        }
+
         // info
-        let [black_turn_area, promotion_area, white_turn_area] = Layout::vertical([2, 1, 2])
+        let [black_turn_area, promotion_area, white_turn_area] = Layout::vertical([3, 1, 3])
             .flex(Flex::SpaceBetween)
             .areas(info_area);
         let turn = self.game.turn();
@@ -214,19 +1154,658 @@ impl Widget for &mut Tui {
             let check_line = Line::raw("check").bg(Color::LightRed).fg(Color::Gray);
             text.push_line(check_line);
         }
+        if self.thinking.is_some() || self.hinting.is_some() {
+            let spinner = SPINNER[self.ticks % SPINNER.len()];
+            text.push_line(format!("{spinner} Thinking…"));
+        }
         text.render(turn_area, buf);
+        if let Some(clock) = &self.clock {
+            for (color, area) in [
+                (PieceColor::White, white_turn_area),
+                (PieceColor::Black, black_turn_area),
+            ] {
+                if let Some(remaining) = clock.remaining(color) {
+                    Line::raw(clock::format(remaining))
+                        .right_aligned()
+                        .render(area.rows().next().unwrap_or(area), buf);
+                }
+            }
+        }
+
+        // the move just stepped to, where promotion choices would otherwise go
+        if let (Some(_), Some(ply)) = (&self.replay, self.history.last()) {
+            let number = self.history.len().div_ceil(2);
+            let dots = if self.history.len() % 2 == 1 {
+                "."
+            } else {
+                "..."
+            };
+            Line::raw(format!("{number}{dots} {}", ply.san)).render(promotion_area, buf);
+        }
 
         // promotion
         if let Some((_, _, click_targets)) = &mut self.selected_promotion {
             click_targets.clear();
-            for (area, piece) in promotion_area.columns().zip(PROMOTIONS) {
-                piece
-                    .render()
-                    .fg(turn.render())
-                    .bg(Color::Gray)
-                    .render(area, buf);
+            for (i, (area, piece)) in promotion_area.columns().zip(PROMOTIONS).enumerate() {
+                let bg = if i == self.promotion_cursor {
+                    self.theme.cursor
+                } else {
+                    Color::Gray
+                };
+                piece.render().fg(turn.render()).bg(bg).render(area, buf);
                 click_targets.insert(area.as_position(), piece);
             }
         }
+
+        // history
+        let last = self.history.len().checked_sub(1);
+        let san = |ply: usize, text: String| {
+            if Some(ply) == last {
+                Span::raw(text).fg(Color::Yellow).bold()
+            } else {
+                Span::raw(text)
+            }
+        };
+        let items = self.history.chunks(2).enumerate().map(|(i, plies)| {
+            let mut line = Line::from(vec![
+                Span::raw(format!("{:>3}. ", i + 1)),
+                san(2 * i, format!("{:<7}", plies[0].san)),
+            ]);
+            if let Some(black) = plies.get(1) {
+                line.push_span(" ");
+                line.push_span(san(2 * i + 1, black.san.clone()));
+            }
+            line
+        });
+        // selecting the last move's row scrolls it into view
+        let mut state = ListState::default().with_selected(last.map(|ply| ply / 2));
+        StatefulWidget::render(List::new(items), history_area, buf, &mut state);
+        self.history_click_targets.clear();
+        for (row, line) in history_area.rows().enumerate() {
+            let white = 2 * (state.offset() + row);
+            for (ply, x, width) in [(white, 0, 5 + 7), (white + 1, 5 + 7 + 1, 7)] {
+                if ply < self.history.len() {
+                    let rect = Rect {
+                        x: line.x + x,
+                        width,
+                        ..line
+                    };
+                    self.history_click_targets.push((rect, ply));
+                }
+            }
+        }
+
+        // help
+        let help = if let Some(outcome) = self.outcome() {
+            format!("{} n new · q quit", outcome.to_string().trim_end())
+        } else if let Some(prompt) = self.prompt {
+            prompt.question().to_string()
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else if self.selected_promotion.is_some() {
+            "←→ choose · enter promote · q quit".to_string()
+        } else if self.replay.is_some() {
+            "←→ step · f flip · q quit".to_string()
+        } else if self.human().is_none() {
+            "f flip · q quit".to_string()
+        } else {
+            // two lines, each short enough for an 80-column terminal
+            "arrows move · enter/space select · u undo · h hint · d draw · r resign\n\
+             n new · s save · l load · f flip · c coords · q quit"
+                .to_string()
+        };
+        // a single line goes on the bottom row
+        for (line, row) in help.lines().rev().zip(help_area.rows().rev()) {
+            Line::raw(line).centered().render(row, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::{
+        crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers},
+        prelude::*,
+    };
+
+    use std::{
+        fs,
+        sync::Arc,
+        time::{Duration, Instant, UNIX_EPOCH},
+    };
+
+    use super::{
+        engine_line, latest_save, search, white_cells, Evaluation, Move, Prompt, Theme, Tui,
+        HISTORY_WIDTH, INFO_WIDTH,
+    };
+    use crate::{
+        game::{square, DrawReason, Game, Outcome, PieceColor, PieceType},
+        openingbook::PolyglotBook,
+    };
+
+    /// exactly fits the board, its labels, the info and history columns and the help lines
+    fn area() -> Rect {
+        Rect::new(
+            0,
+            0,
+            1 + 8 * 2 + 1 + 1 + 1 + INFO_WIDTH + 1 + HISTORY_WIDTH,
+            1 + 8 + 1 + 1 + 1 + 2,
+        )
+    }
+
+    /// a key pressed without modifiers
+    fn press(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn help_fits_80_columns() {
+        let area = Rect::new(0, 0, 80, area().height);
+        let mut buf = Buffer::empty(area);
+        Tui::new(Game::new(), [None, None], false).render(area, &mut buf);
+        let row = |y| -> String { (0..area.width).map(|x| buf[(x, y)].symbol()).collect() };
+        let (first, second) = (row(area.height - 2), row(area.height - 1));
+        assert!(first.trim().starts_with("arrows move"), "{first}");
+        assert!(first.trim().ends_with("r resign"), "{first}");
+        assert!(second.trim().starts_with("n new"), "{second}");
+        assert!(second.trim().ends_with("q quit"), "{second}");
+    }
+
+    #[test]
+    fn coordinates() {
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        Tui::new(Game::new(), [None, None], false).render(area, &mut buf);
+        // below the row of black's captures
+        assert_eq!(buf[(0, 1)].symbol(), "8");
+        assert_eq!(buf[(0, 8)].symbol(), "1");
+        assert_eq!(buf[(1, 9)].symbol(), "a");
+        assert_eq!(buf[(15, 9)].symbol(), "h");
+        // the board itself starts one column right of the rank labels
+        assert_eq!(buf[(1, 1)].symbol(), "♜");
+        assert_eq!(buf[(9, 8)].symbol(), "♚");
+
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        assert!(!tui.handle(press(KeyCode::Char('c'))));
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        assert_eq!(buf[(0, 1)].symbol(), " ");
+        assert_eq!(buf[(1, 9)].symbol(), " ");
+        assert_eq!(buf[(1, 1)].symbol(), "♜");
+    }
+
+    #[test]
+    fn history() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        for (from, to) in [("e2", "e4"), ("d7", "d5"), ("e4", "d5")] {
+            tui.play(Move::Normal(square(from), square(to)));
+        }
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        let history_x = 1 + 8 * 2 + 1 + 1 + 1 + INFO_WIDTH + 1;
+        let row = |y| {
+            (history_x..area.width)
+                .map(|x| buf[(x, y)].symbol())
+                .collect::<String>()
+        };
+        assert_eq!(row(1).trim_end(), "  1. e4      d5");
+        assert_eq!(row(2).trim_end(), "  2. exd5");
+        // only the last move stands out
+        assert!(buf[(history_x + 5, 2)].modifier.contains(Modifier::BOLD));
+        assert!(!buf[(history_x + 5 + 7 + 1, 1)]
+            .modifier
+            .contains(Modifier::BOLD));
+        // white's captures are below the file labels
+        let captures: String = (0..4).map(|x| buf[(x, 10)].symbol()).collect();
+        assert_eq!(captures, "♟ +1");
+
+        // clicking on 1... d5 undoes 2. exd5
+        tui.jump(1);
+        assert_eq!(tui.history.len(), 2);
+        assert_eq!(tui.game.turn(), PieceColor::White);
+    }
+
+    #[test]
+    fn flipped_click() {
+        let area = area();
+        let top_left = ratatui::layout::Position { x: 1, y: 1 };
+        let clicked = |tui: &Tui| {
+            tui.click_targets
+                .iter()
+                .find(|(rect, _)| rect.contains(top_left))
+                .map(|&(_, position)| position.to_string())
+        };
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.render(area, &mut Buffer::empty(area));
+        assert_eq!(clicked(&tui).as_deref(), Some("a8"));
+        tui.flipped = true;
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        assert_eq!(clicked(&tui).as_deref(), Some("h1"));
+        assert_eq!(buf[(0, 1)].symbol(), "1");
+        assert_eq!(buf[(1, 9)].symbol(), "h");
+    }
+
+    #[test]
+    fn keyboard() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        // the cursor starts on e2
+        for code in [KeyCode::Enter, KeyCode::Up, KeyCode::Up, KeyCode::Char(' ')] {
+            assert!(!tui.handle(press(code)));
+        }
+        assert_eq!(tui.history.len(), 1);
+        assert_eq!(tui.history[0].san, "e4");
+        assert!(tui.handle(press(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn replay() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.replay_mode("1. e4 e5 2. Nf3 1-0").unwrap();
+        assert!(tui.history.is_empty());
+        for code in [
+            KeyCode::Right,
+            KeyCode::Right,
+            KeyCode::Right,
+            KeyCode::Right,
+        ] {
+            assert!(!tui.handle(press(code)));
+        }
+        assert_eq!(tui.history.len(), 3);
+        assert!(!tui.handle(press(KeyCode::Left)));
+        assert_eq!(tui.history.len(), 2);
+        assert_eq!(tui.game.turn(), PieceColor::White);
+
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        // the info column's middle row
+        let info_x = 1 + 8 * 2 + 1 + 1 + 1;
+        let info: String = (info_x..info_x + INFO_WIDTH)
+            .map(|x| buf[(x, 5)].symbol())
+            .collect();
+        assert_eq!(info.trim_end(), "1... e5");
+        // the board does not respond to selection
+        assert!(!tui.handle(press(KeyCode::Enter)));
+        assert!(tui.selected_tile.is_none());
+    }
+
+    #[test]
+    fn hint() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        assert!(!tui.handle(press(KeyCode::Char('h'))));
+        // a second press while the first hint is being worked out is ignored
+        assert!(!tui.handle(press(KeyCode::Char('h'))));
+        assert_eq!(tui.hints_used(), 1);
+        let (hint, _, _) = tui.hinting.take().unwrap().recv().unwrap();
+        assert!(tui.game.is_legal(hint));
+        tui.hint = Some(hint);
+
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        let Move::Normal(from, to) = hint else {
+            panic!("promotion from the starting position")
+        };
+        for position in [from, to] {
+            let (x, y) = (1 + 2 * position.file(), 1 + 7 - position.rank());
+            assert_eq!(buf[(x.into(), y.into())].bg, Color::LightCyan);
+        }
+
+        tui.play(Move::Normal(from, to));
+        assert!(tui.hint.is_none());
+    }
+
+    #[test]
+    fn captures_with_promotion() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.game = Game::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        tui.play(Move::Promote(square("b7"), square("a8"), PieceType::Queen));
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        // the pawn that promoted counts as the queen it became, not as a pawn lost
+        let captures: String = (0..4).map(|x| buf[(x, 10)].symbol()).collect();
+        assert_eq!(captures, "♜ +9");
+        let captures: String = (0..4).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(captures.trim_end(), "");
+    }
+
+    #[test]
+    fn check_highlight() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        for (from, to) in [("e2", "e4"), ("f7", "f6"), ("d1", "h5")] {
+            tui.play(Move::Normal(square(from), square(to)));
+        }
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        // the black king on e8, and the queen that checks it from h5
+        assert_eq!(buf[(1 + 2 * 4, 1)].bg, Color::LightRed);
+        assert_eq!(buf[(1 + 2 * 7, 1 + 3)].bg, Color::LightYellow);
+        assert_eq!(buf[(1 + 2 * 3, 1 + 7)].bg, Color::LightYellow);
+    }
+
+    #[test]
+    fn undo() {
+        let mut tui = Tui::new(Game::new(), [None, Some(1)], false);
+        // undoing before any move is a no-op
+        assert!(!tui.handle(press(KeyCode::Char('u'))));
+        assert!(tui.game == Game::new());
+        // the AI's reply is taken back along with the move it replied to
+        tui.play(Move::Normal(square("e2"), square("e4")));
+        tui.play(Move::Normal(square("e7"), square("e5")));
+        tui.play(Move::Normal(square("d2"), square("d4")));
+        tui.play(Move::Normal(square("d7"), square("d5")));
+        assert!(!tui.handle(press(KeyCode::Char('u'))));
+        assert_eq!(tui.history.len(), 2);
+        assert_eq!(tui.game.turn(), PieceColor::White);
+        // as is a reply the AI is still thinking about
+        tui.play(Move::Normal(square("g1"), square("f3")));
+        tui.thinking = Some(std::sync::mpsc::channel().1);
+        assert!(!tui.handle(press(KeyCode::Char('u'))));
+        assert_eq!(tui.history.len(), 2);
+        assert!(tui.thinking.is_none());
+
+        // the AI's opening move as white is not the human's to take back
+        let mut tui = Tui::new(Game::new(), [Some(1), None], false);
+        tui.play(Move::Normal(square("e2"), square("e4")));
+        assert!(!tui.handle(press(KeyCode::Char('u'))));
+        assert_eq!(tui.history.len(), 1);
+    }
+
+    #[test]
+    fn new_game() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.play(Move::Normal(square("e2"), square("e4")));
+        tui.activate(square("e7"));
+        // abandoning the game in progress needs confirming
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert_eq!(tui.prompt.map(Prompt::question), Some("New game? y/n"));
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert_eq!(tui.history.len(), 1);
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert!(!tui.handle(press(KeyCode::Char('y'))));
+        assert!(tui.game == Game::new());
+        assert!(tui.history.is_empty());
+        assert!(tui.selected_tile.is_none());
+
+        // once the game is over, n starts another and q quits
+        for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            tui.play(Move::Normal(square(from), square(to)));
+        }
+        assert_eq!(tui.outcome(), Some(Outcome::Win(PieceColor::Black)));
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        let help: String = (0..area.width)
+            .map(|x| buf[(x, area.height - 1)].symbol())
+            .collect();
+        assert!(help.contains("n new · q quit"), "{help}");
+        assert!(!tui.handle_game_over(press(KeyCode::Char('x'))));
+        assert_eq!(tui.history.len(), 4);
+        assert!(!tui.handle_game_over(press(KeyCode::Char('n'))));
+        assert!(tui.game == Game::new());
+        assert_eq!(tui.outcome(), None);
+        tui.play(Move::Normal(square("f2"), square("f3")));
+        tui.outcome = Some(Outcome::Resignation(PieceColor::White));
+        assert!(tui.handle_game_over(press(KeyCode::Char('q'))));
+
+        tui.replay_mode("1. d4 d5").unwrap();
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert!(tui.replay.is_none());
+    }
+
+    #[test]
+    fn resign_and_draw() {
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        // the other player declines
+        assert!(!tui.handle(press(KeyCode::Char('d'))));
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert_eq!(tui.outcome(), None);
+        assert!(tui.game == Game::new());
+        // and then accepts
+        assert!(!tui.handle(press(KeyCode::Char('d'))));
+        assert!(!tui.handle(press(KeyCode::Char('y'))));
+        assert_eq!(tui.outcome(), Some(Outcome::Draw(DrawReason::Agreed)));
+
+        // the knights go out and back twice, so the starting position comes up a third time
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        for _ in 0..2 {
+            for (from, to) in [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")] {
+                assert_eq!(tui.outcome(), None);
+                tui.play(Move::Normal(square(from), square(to)));
+            }
+        }
+        assert_eq!(
+            tui.outcome(),
+            Some(Outcome::Draw(DrawReason::ThreefoldRepetition))
+        );
+
+        // the fifty-move rule counts the plies a FEN says were played before
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").expect("valid fen");
+        let mut tui = Tui::new(game, [None, None], false);
+        assert_eq!(tui.outcome(), None);
+        tui.play(Move::Normal(square("a1"), square("a2")));
+        assert_eq!(
+            tui.outcome(),
+            Some(Outcome::Draw(DrawReason::FiftyMoveRule))
+        );
+
+        // the human resigns even while the AI is to move, once sure
+        let mut tui = Tui::new(Game::new(), [Some(1), None], false);
+        assert!(!tui.handle(press(KeyCode::Char('r'))));
+        assert_eq!(tui.prompt.map(Prompt::question), Some("Resign? y/n"));
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert_eq!(tui.outcome(), None);
+        assert!(!tui.handle(press(KeyCode::Char('r'))));
+        assert!(!tui.handle(press(KeyCode::Char('y'))));
+        assert_eq!(tui.outcome(), Some(Outcome::Resignation(PieceColor::Black)));
+        assert_eq!(
+            tui.outcome().and_then(Outcome::winner),
+            Some(PieceColor::White)
+        );
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        let help: String = (0..area.width)
+            .map(|x| buf[(x, area.height - 1)].symbol())
+            .collect();
+        assert!(help.contains("Black resigned. White wins!"), "{help}");
+    }
+
+    #[test]
+    fn book() {
+        let book = Arc::new(PolyglotBook::mainlines());
+        let game = Game::new();
+        let receiver = search(&game, 1, Duration::ZERO, Some(0), Some(book.clone()));
+        let (r#move, pv, evaluation) = receiver.recv().unwrap();
+        assert_eq!(Some(r#move), book.probe(&game, Some(0)));
+        assert_eq!((pv, evaluation), (vec![r#move], None));
+    }
+
+    #[test]
+    fn spectate() {
+        let mut tui = Tui::new(Game::new(), [Some(1), Some(1)], false);
+        tui.set_ai_delay(Duration::from_millis(50));
+        let start = Instant::now();
+        tui.thinking = Some(search(&tui.game, 1, tui.ai_delay, None, None));
+        let (r#move, pv, _) = tui.thinking.take().unwrap().recv().unwrap();
+        assert_eq!(pv, vec![r#move]);
+        assert_eq!(engine_line(&pv), format!("Engine: {move}"));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        tui.play(r#move);
+        // there is no human to resign, offer a draw or take back a move
+        for key in ['r', 'd', 'u'] {
+            assert!(!tui.handle(press(KeyCode::Char(key))));
+        }
+        assert_eq!(tui.outcome(), None);
+        assert_eq!(tui.prompt.map(Prompt::question), None);
+        assert_eq!(tui.history.len(), 1);
+        // but the board can still be flipped
+        assert!(!tui.handle(press(KeyCode::Char('f'))));
+        assert!(tui.flipped);
+        assert!(tui.handle(press(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("chess-save-{}", std::process::id()));
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.save_dir = dir.clone();
+        assert!(!tui.handle(press(KeyCode::Char('l'))));
+        assert!(tui.message.as_deref().unwrap().starts_with("No saved game"));
+
+        tui.play(Move::Normal(square("e2"), square("e4")));
+        let saved = tui.game.clone();
+        assert!(!tui.handle(press(KeyCode::Char('s'))));
+        let message = tui.message.clone().unwrap();
+        assert!(message.starts_with("Saved to "), "{message}");
+        let path = latest_save(&dir).unwrap().unwrap();
+        assert!(message.ends_with(&path.display().to_string()), "{message}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1. e4 *\n");
+        // the confirmation goes away by itself
+        tui.expire_message(Instant::now());
+        assert!(tui.message.is_some());
+        tui.expire_message(Instant::now() + Duration::from_secs(3));
+        assert!(tui.message.is_none());
+
+        tui.play(Move::Normal(square("e7"), square("e5")));
+        assert!(!tui.handle(press(KeyCode::Char('l'))));
+        assert!(tui.game == saved);
+        assert_eq!(tui.history.len(), 1);
+        assert_eq!(tui.history[0].san, "e4");
+
+        // a resignation is recorded in the result
+        let output = dir.join("game.pgn");
+        tui.set_output(output.clone());
+        assert!(!tui.handle(press(KeyCode::Char('r'))));
+        assert!(!tui.handle(press(KeyCode::Char('y'))));
+        assert!(!tui.handle(press(KeyCode::Char('s'))));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "1. e4 1-0\n");
+
+        tui.set_output(dir.join("save.pgn").join("game.pgn"));
+        fs::write(dir.join("save.pgn"), "").unwrap();
+        assert!(!tui.handle(press(KeyCode::Char('s'))));
+        let message = tui.message.clone().unwrap();
+        assert!(message.starts_with("Could not save"), "{message}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn timestamp() {
+        let time = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+        assert_eq!(super::timestamp(time(0)), "19700101_000000");
+        assert_eq!(super::timestamp(time(1_704_110_400)), "20240101_120000");
+        assert_eq!(super::timestamp(time(1_709_251_199)), "20240229_235959");
+        assert_eq!(super::timestamp(time(4_107_542_400)), "21000301_000000");
+    }
+
+    #[test]
+    fn clock() {
+        let row = |buf: &Buffer, y| -> String {
+            (0..area().width).map(|x| buf[(x, y)].symbol()).collect()
+        };
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.set_time_control([Some("1+2".parse().unwrap()); 2]);
+        tui.play(Move::Normal("e2".parse().unwrap(), "e4".parse().unwrap()));
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        // black's clock beside black's turn, above white's, which has gained the increment
+        assert!(row(&buf, 1).contains("Black 01:00"), "{}", row(&buf, 1));
+        assert!(row(&buf, 6).contains("01:02"), "{}", row(&buf, 6));
+
+        tui.tick_clock(Instant::now() + Duration::from_secs(61));
+        assert_eq!(tui.outcome(), Some(Outcome::Timeout(PieceColor::Black)));
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        assert!(row(&buf, 1).contains("00:00"), "{}", row(&buf, 1));
+        let help = row(&buf, area.height - 1);
+        assert!(
+            help.contains("Black ran out of time. White wins!"),
+            "{help}"
+        );
+
+        // a new game gets fresh clocks
+        assert!(!tui.handle(press(KeyCode::Char('n'))));
+        assert_eq!(tui.outcome(), None);
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        assert!(row(&buf, 6).contains("White 01:00"), "{}", row(&buf, 6));
+    }
+
+    #[test]
+    fn theme() {
+        let theme: Theme = "colorblind".parse().unwrap();
+        assert!("plaid".parse::<Theme>().is_err());
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        tui.set_theme(theme);
+        tui.play(Move::Normal(square("e2"), square("e4")));
+        let area = area();
+        let mut buf = Buffer::empty(area);
+        tui.render(area, &mut buf);
+        // e4 is light, and the cursor is still on e2
+        assert_eq!(buf[(1 + 2 * 4, 1 + 4)].bg, theme.last_move_light);
+        assert_eq!(buf[(1 + 2 * 4, 1 + 6)].bg, theme.cursor);
+        assert_eq!(buf[(1 + 2 * 4, 1 + 5)].bg, theme.dark_square);
+
+        // every theme tells the squares apart, here a8 and b8
+        for name in ["classic", "green", "blue", "high-contrast", "colorblind"] {
+            let mut tui = Tui::new(Game::new(), [None, None], false);
+            tui.set_theme(name.parse().unwrap());
+            let mut buf = Buffer::empty(area);
+            tui.render(area, &mut buf);
+            let (light, dark) = (buf[(1, 1)].bg, buf[(1 + 2, 1)].bg);
+            assert_eq!(light, tui.theme.light_square, "{name}");
+            assert_ne!(light, dark, "{name}");
+        }
+    }
+
+    #[test]
+    fn evaluation_bar() {
+        assert_eq!(white_cells(Evaluation::Estimate(0)), 4);
+        assert_eq!(white_cells(Evaluation::Estimate(130)), 5);
+        assert_eq!(white_cells(Evaluation::Estimate(-900)), 0);
+        assert_eq!(
+            white_cells(Evaluation::Outcome(Outcome::Win(PieceColor::White))),
+            8
+        );
+        assert_eq!(
+            white_cells(Evaluation::Outcome(Outcome::Draw(DrawReason::Stalemate))),
+            4
+        );
+
+        let mut tui = Tui::new(Game::new(), [None, None], false);
+        let area = area();
+        let bar = |tui: &mut Tui| {
+            let mut buf = Buffer::empty(area);
+            tui.render(area, &mut buf);
+            (1..=8)
+                .map(|y| buf[(1 + 8 * 2 + 1, y)].symbol().to_string())
+                .collect::<String>()
+        };
+        // nothing to show before the first move
+        assert_eq!(bar(&mut tui).trim(), "");
+        tui.play_evaluated(
+            Move::Normal(square("e2"), square("e4")),
+            Some(Evaluation::Estimate(-200)),
+        );
+        assert_eq!(bar(&mut tui), "░░░░░░▓▓");
+        // white's share stays on white's side
+        tui.flipped = true;
+        assert_eq!(bar(&mut tui), "▓▓░░░░░░");
+        // a human's move is judged on another thread, keeping the last judgement until then
+        tui.play(Move::Normal(square("e7"), square("e5")));
+        assert_eq!(bar(&mut tui), "▓▓░░░░░░");
+        let ply = tui.history.last_mut().unwrap();
+        let (_, _, evaluation) = ply.evaluating.take().unwrap().recv().unwrap();
+        ply.evaluation = evaluation;
+        assert_ne!(bar(&mut tui), "▓▓░░░░░░");
+        // a replayed move is not judged
+        tui.play_evaluated(Move::Normal(square("g1"), square("f3")), None);
+        assert!(tui.history.last().unwrap().evaluation.is_none());
+        assert!(tui.history.last().unwrap().evaluating.is_none());
     }
 }
@@ -0,0 +1,55 @@
+//! speaking moves aloud through the system's text-to-speech, for players who cannot see the board
+
+use std::process::{Command, Stdio};
+
+use crate::game::{Game, Move, Outcome};
+
+/// e.g. "Knight from g1 to f3, check".
+/// REQUIRES: the move is legal in `game`
+pub fn announcement(game: &Game, r#move: Move) -> String {
+    let (from, to) = (r#move.from(), r#move.to());
+    let piece = game.get(from).expect("announcement precondition").piece;
+    let mut text = if game.is_castling(from, to) {
+        let side = if to.file() > from.file() {
+            "kingside"
+        } else {
+            "queenside"
+        };
+        format!("Castles {side}")
+    } else if game.is_capture(from, to) {
+        format!("{piece} from {from} takes on {to}")
+    } else {
+        format!("{piece} from {from} to {to}")
+    };
+    if let Some(piece_type) = r#move.promotion() {
+        text.push_str(&format!(", promotes to {piece_type}"));
+    }
+    let after = game.apply_move_unchecked(r#move);
+    match after.status() {
+        Some(Outcome::Win(_)) => text.push_str(", checkmate"),
+        Some(Outcome::Draw(reason)) => text.push_str(&format!(", {reason}")),
+        _ if after.check() => text.push_str(", check"),
+        _ => {}
+    }
+    text
+}
+
+/// speaks `text` in the background with `say` on macOS or `espeak` elsewhere.
+/// Failure, e.g. because neither is installed, is silently ignored.
+pub fn speak(text: &str) {
+    let program = if cfg!(target_os = "macos") {
+        "say"
+    } else {
+        "espeak"
+    };
+    if let Ok(mut child) = Command::new(program)
+        .arg(text)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        // reap the process once it is done speaking
+        std::thread::spawn(move || child.wait());
+    }
+}
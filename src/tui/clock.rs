@@ -0,0 +1,159 @@
+//! chess clocks, counting down each player's time while they are to move
+
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::game::PieceColor;
+
+/// how much time each player starts with, and how much they gain with each move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseTimeControlError(String);
+
+impl Display for ParseTimeControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid time control {:?}: expected minutes and optionally an increment in seconds, \
+             such as \"5+3\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseTimeControlError {}
+
+/// e.g. `5+3` for five minutes each with three seconds added per move, or `10` for ten minutes
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseTimeControlError(s.to_string());
+        let (minutes, seconds) = s.split_once('+').unwrap_or((s, "0"));
+        let minutes: f64 = minutes.parse().map_err(|_| error())?;
+        let seconds: f64 = seconds.parse().map_err(|_| error())?;
+        let base = Duration::try_from_secs_f64(minutes * 60.0).map_err(|_| error())?;
+        let increment = Duration::try_from_secs_f64(seconds).map_err(|_| error())?;
+        if base.is_zero() {
+            return Err(error());
+        }
+        Ok(Self { base, increment })
+    }
+}
+
+pub(super) struct Clock {
+    /// indexed by color, None for a player with unlimited time
+    remaining: [Option<Duration>; 2],
+    /// indexed by color
+    increments: [Duration; 2],
+    /// when the time of the player to move was last counted down
+    last_tick: Instant,
+}
+
+impl Clock {
+    /// `time_controls` is indexed by color, None for a player with unlimited time
+    pub(super) fn new(time_controls: [Option<TimeControl>; 2], now: Instant) -> Self {
+        Self {
+            remaining: time_controls.map(|time_control| time_control.map(|tc| tc.base)),
+            increments: time_controls
+                .map(|time_control| time_control.map_or(Duration::ZERO, |tc| tc.increment)),
+            last_tick: now,
+        }
+    }
+
+    /// None if `color` has unlimited time
+    pub(super) fn remaining(&self, color: PieceColor) -> Option<Duration> {
+        self.remaining[color as usize]
+    }
+
+    /// counts down the time of `color`, who has been to move since the last tick, and returns
+    /// whether it has run out
+    pub(super) fn tick(&mut self, color: PieceColor, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_tick);
+        self.last_tick = now;
+        self.remaining[color as usize].is_some_and(|remaining| {
+            let remaining = remaining.saturating_sub(elapsed);
+            self.remaining[color as usize] = Some(remaining);
+            remaining.is_zero()
+        })
+    }
+
+    /// counts down the time of `color`, who has just moved, and then adds their increment
+    pub(super) fn moved(&mut self, color: PieceColor, now: Instant) {
+        if !self.tick(color, now) {
+            if let Some(remaining) = &mut self.remaining[color as usize] {
+                *remaining += self.increments[color as usize];
+            }
+        }
+    }
+}
+
+/// e.g. `04:59.9`, rounding up so that a clock only reads `00:00.0` once it has run out
+pub(super) fn format(remaining: Duration) -> String {
+    let tenths = remaining.as_millis().div_ceil(100);
+    let seconds = tenths / 10;
+    format!("{:02}:{:02}.{}", seconds / 60, seconds % 60, tenths % 10)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{format, Clock, TimeControl};
+    use crate::game::PieceColor;
+
+    #[test]
+    fn time_control() {
+        let seconds = Duration::from_secs;
+        assert_eq!(
+            "5+3".parse(),
+            Ok(TimeControl {
+                base: seconds(300),
+                increment: seconds(3)
+            })
+        );
+        assert_eq!(
+            "0.5".parse(),
+            Ok(TimeControl {
+                base: seconds(30),
+                increment: seconds(0)
+            })
+        );
+        for invalid in ["", "+3", "5+", "0", "-1", "five", "5+3+1"] {
+            assert!(invalid.parse::<TimeControl>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn clock() {
+        let start = Instant::now();
+        let seconds = Duration::from_secs;
+        let time_control = "1+2".parse().unwrap();
+        let mut clock = Clock::new([Some(time_control); 2], start);
+        assert!(!clock.tick(PieceColor::White, start + seconds(10)));
+        clock.moved(PieceColor::White, start + seconds(15));
+        assert_eq!(clock.remaining(PieceColor::White), Some(seconds(47)));
+        assert_eq!(clock.remaining(PieceColor::Black), Some(seconds(60)));
+        assert!(clock.tick(PieceColor::Black, start + seconds(100)));
+        assert_eq!(format(seconds(47)), "00:47.0");
+        assert_eq!(format(Duration::ZERO), "00:00.0");
+        assert_eq!(format(Duration::from_millis(59_901)), "01:00.0");
+        assert_eq!(format(Duration::from_millis(9_250)), "00:09.3");
+
+        // a player without a clock never runs out
+        let mut clock = Clock::new([None, Some("1".parse().unwrap())], start);
+        assert!(!clock.tick(PieceColor::White, start + seconds(1000)));
+        clock.moved(PieceColor::White, start + seconds(1001));
+        assert_eq!(clock.remaining(PieceColor::White), None);
+        assert!(!clock.tick(PieceColor::Black, start + seconds(1031)));
+        assert_eq!(clock.remaining(PieceColor::Black), Some(seconds(30)));
+    }
+}
@@ -0,0 +1,147 @@
+//! the colors the board is drawn in
+
+use std::{fmt::Display, str::FromStr};
+
+use ratatui::style::Color;
+
+use crate::game::Position;
+
+/// background colors of the squares and their highlights, and foreground colors of the marks
+/// drawn on them. Pieces are drawn in white and black, so squares need to be neither.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub light_square: Color,
+    pub dark_square: Color,
+    /// the squares the last move was from and to, on light and dark squares respectively
+    pub last_move_light: Color,
+    pub last_move_dark: Color,
+    /// the mark on the selected piece
+    pub selection: Color,
+    /// the mark on each square the selected piece can move to
+    pub legal_move: Color,
+    /// the king in check, pulsing between the two
+    pub check: [Color; 2],
+    /// the square highlighted for keyboard input
+    pub cursor: Color,
+    /// the squares of the move the AI suggests
+    pub hint: Color,
+}
+
+impl Theme {
+    pub const CLASSIC: Self = Self {
+        light_square: Color::Gray,
+        dark_square: Color::DarkGray,
+        last_move_light: Color::LightYellow,
+        last_move_dark: Color::Yellow,
+        selection: Color::LightYellow,
+        legal_move: Color::LightGreen,
+        check: [Color::LightRed, Color::Red],
+        cursor: Color::LightBlue,
+        hint: Color::LightCyan,
+    };
+
+    /// the green and cream of many online boards
+    pub const GREEN: Self = Self {
+        light_square: Color::Rgb(238, 238, 210),
+        dark_square: Color::Rgb(118, 150, 86),
+        last_move_light: Color::Rgb(246, 246, 105),
+        last_move_dark: Color::Rgb(186, 202, 43),
+        selection: Color::LightYellow,
+        legal_move: Color::LightBlue,
+        check: [Color::LightRed, Color::Red],
+        cursor: Color::Blue,
+        hint: Color::LightCyan,
+    };
+
+    /// grayish blues, with highlights away from blue
+    pub const BLUE: Self = Self {
+        light_square: Color::Rgb(222, 227, 230),
+        dark_square: Color::Rgb(140, 162, 173),
+        last_move_light: Color::Rgb(246, 246, 105),
+        last_move_dark: Color::Rgb(186, 202, 43),
+        selection: Color::LightYellow,
+        legal_move: Color::LightGreen,
+        check: [Color::LightRed, Color::Red],
+        cursor: Color::Magenta,
+        hint: Color::LightMagenta,
+    };
+
+    /// highlights from the Okabe–Ito palette with no red and green pair, told apart by
+    /// lightness as well as hue, such as check flashing between vermillion and yellow
+    pub const HIGH_CONTRAST: Self = Self {
+        light_square: Color::Gray,
+        dark_square: Color::DarkGray,
+        last_move_light: Color::Rgb(240, 228, 66),
+        last_move_dark: Color::Rgb(230, 159, 0),
+        selection: Color::Rgb(0, 114, 178),
+        legal_move: Color::Rgb(86, 180, 233),
+        check: [Color::Rgb(213, 94, 0), Color::Rgb(240, 228, 66)],
+        cursor: Color::Rgb(204, 121, 167),
+        hint: Color::Rgb(0, 158, 115),
+    };
+
+    /// avoids telling red from green, using colors from the Okabe–Ito palette
+    pub const COLORBLIND: Self = Self {
+        light_square: Color::Gray,
+        dark_square: Color::DarkGray,
+        last_move_light: Color::Rgb(86, 180, 233),
+        last_move_dark: Color::Rgb(0, 114, 178),
+        selection: Color::Rgb(240, 228, 66),
+        legal_move: Color::Rgb(240, 228, 66),
+        check: [Color::Rgb(230, 159, 0), Color::Rgb(213, 94, 0)],
+        cursor: Color::Rgb(204, 121, 167),
+        hint: Color::Rgb(0, 158, 115),
+    };
+
+    pub fn square(&self, position: Position) -> Color {
+        if position.is_light() {
+            self.light_square
+        } else {
+            self.dark_square
+        }
+    }
+
+    pub fn last_move(&self, position: Position) -> Color {
+        if position.is_light() {
+            self.last_move_light
+        } else {
+            self.last_move_dark
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseThemeError(String);
+
+impl Display for ParseThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown theme {:?}: expected classic, green, blue, high-contrast or colorblind",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseThemeError {}
+
+impl FromStr for Theme {
+    type Err = ParseThemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Self::CLASSIC),
+            "green" => Ok(Self::GREEN),
+            "blue" => Ok(Self::BLUE),
+            "high-contrast" => Ok(Self::HIGH_CONTRAST),
+            "colorblind" => Ok(Self::COLORBLIND),
+            _ => Err(ParseThemeError(s.to_string())),
+        }
+    }
+}
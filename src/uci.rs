@@ -0,0 +1,122 @@
+use std::{
+    io::{self, BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    ai::{Engine, Evaluation, Move, SearchResult},
+    game::{Game, PieceColor},
+};
+
+/// search depth used when `go` does not specify one
+const DEFAULT_DEPTH: usize = 4;
+
+/// the `score` of an `info` line, from the point of view of `turn`: `cp` in centipawns, or
+/// `mate` in moves, negative if `turn` is the one mated
+fn score(evaluation: Evaluation, turn: PieceColor, pv: &[Move]) -> String {
+    match evaluation {
+        Evaluation::Estimate(cp) => match turn {
+            PieceColor::White => format!("cp {cp}"),
+            PieceColor::Black => format!("cp {}", -cp),
+        },
+        Evaluation::Outcome(outcome) => match outcome.winner() {
+            None => "cp 0".to_string(),
+            Some(winner) if winner == turn => format!("mate {}", pv.len().div_ceil(2)),
+            Some(_) => format!("mate -{}", pv.len() / 2),
+        },
+    }
+}
+
+/// the `info` line reporting a finished search
+fn info(result: &SearchResult, turn: PieceColor) -> String {
+    let mut line = format!(
+        "info depth {} score {} nodes {} pv",
+        result.depth,
+        score(result.evaluation, turn, &result.pv),
+        result.nodes
+    );
+    for r#move in &result.pv {
+        line.push_str(&format!(" {move}"));
+    }
+    line
+}
+
+/// handles the arguments of a `position` command.
+/// Returns None if the position or any of its moves is invalid.
+fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Game> {
+    let mut game = match tokens.next()? {
+        "startpos" => Game::new(),
+        "fen" => {
+            let fen: Vec<&str> = tokens.by_ref().take_while(|&t| t != "moves").collect();
+            Game::from_fen(&fen.join(" ")).ok()?
+        }
+        _ => return None,
+    };
+    for token in tokens.skip_while(|&t| t == "moves") {
+        game = game.apply_move_unchecked(Move::from_uci_str(token, &game).ok()?);
+    }
+    Some(game)
+}
+
+/// speaks the Universal Chess Interface protocol until `quit` or end of input
+pub fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut game = Game::new();
+    let mut engine = Engine::new();
+    for line in input.lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                writeln!(output, "id name {}", env!("CARGO_PKG_NAME"))?;
+                writeln!(output, "id author vrama628")?;
+                writeln!(output, "uciok")?;
+            }
+            Some("isready") => writeln!(output, "readyok")?,
+            Some("ucinewgame") => {
+                game = Game::new();
+                engine = Engine::new();
+            }
+            Some("position") => match parse_position(tokens) {
+                Some(position) => game = position,
+                None => writeln!(output, "info string invalid position: {line}")?,
+            },
+            Some("go") => {
+                // other limits fall back to the default depth
+                let mut depth = DEFAULT_DEPTH;
+                let mut movetime = None;
+                while let Some(token) = tokens.next() {
+                    match token {
+                        "depth" => {
+                            if let Some(n) = tokens.next().and_then(|n| n.parse::<usize>().ok()) {
+                                depth = n.max(1);
+                            }
+                        }
+                        "movetime" => {
+                            movetime = tokens
+                                .next()
+                                .and_then(|ms| ms.parse().ok())
+                                .map(Duration::from_millis);
+                        }
+                        _ => {}
+                    }
+                }
+                if game.status().is_some() {
+                    writeln!(output, "bestmove 0000")?;
+                } else {
+                    let result = match movetime {
+                        Some(budget) => engine.search_for(&game, budget),
+                        None => engine.search(&game, depth),
+                    };
+                    writeln!(output, "{}", info(&result, game.turn()))?;
+                    writeln!(output, "bestmove {}", result.best)?;
+                }
+            }
+            // searches run to completion before the next command is read
+            Some("stop") => {}
+            Some("quit") => break,
+            _ => {}
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
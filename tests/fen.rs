@@ -0,0 +1,64 @@
+use std::process::Command;
+
+use chess::game::{DrawReason, FenError, Game, Outcome, PieceColor, PositionError};
+
+#[test]
+fn endgames() {
+    let status = |fen| Game::from_fen(fen).expect("valid fen").status();
+    // a back-rank mate
+    assert_eq!(
+        status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"),
+        Some(Outcome::Win(PieceColor::White))
+    );
+    assert_eq!(
+        status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+        Some(Outcome::Draw(DrawReason::Stalemate))
+    );
+    // king and queen against king is won, but not yet over
+    assert_eq!(status("8/8/8/4k3/8/8/8/3QK3 w - - 0 1"), None);
+}
+
+#[test]
+fn validate_fen() {
+    assert_eq!(Game::validate_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1"), Ok(()));
+    assert_eq!(
+        Game::validate_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+        Err(FenError::Position(PositionError::OpponentInCheck(
+            PieceColor::Black
+        )))
+    );
+    assert_eq!(
+        Game::validate_fen("4k3/8/8/8/8/8/8/8 w - - 0 1"),
+        Err(FenError::Position(PositionError::KingCount(
+            PieceColor::White
+        )))
+    );
+    assert_eq!(
+        Game::validate_fen("4k3/8/8/8/8/8/8/4K3 w"),
+        Err(FenError::MissingField("castling"))
+    );
+}
+
+#[test]
+fn cli() {
+    let chess = || Command::new(env!("CARGO_BIN_EXE_chess"));
+    let output = chess()
+        .args(["--fen", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"])
+        .args(["--from", "a1", "--to", "a8"])
+        .output()
+        .expect("run chess");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    assert_eq!(stdout.lines().next(), Some("Ra8#"));
+
+    let output = chess()
+        .args(["--fen", "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"])
+        .output()
+        .expect("run chess");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).expect("utf-8");
+    assert!(
+        stderr.contains("Black is in check but it is White's turn"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,30 @@
+use std::process::Command;
+
+#[test]
+fn cli() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--perft", "4"])
+        .output()
+        .expect("run chess");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 21);
+    assert!(lines.contains(&"e2e4: 13160"));
+    assert_eq!(lines.last(), Some(&"Total: 197281"));
+
+    // the usual starting position, by its Chess960 number
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--chess960", "518", "--perft", "2"])
+        .output()
+        .expect("run chess");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    assert_eq!(stdout.lines().last(), Some("Total: 400"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--chess960", "960", "--perft", "2"])
+        .output()
+        .expect("run chess");
+    assert!(!output.status.success());
+}
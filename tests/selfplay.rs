@@ -0,0 +1,25 @@
+use std::process::Command;
+
+#[test]
+fn mate_in_one() {
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--selfplay", "--white-depth", "2", "--black-depth", "1"])
+        .args(["--fen", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"])
+        .output()
+        .expect("run chess");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).expect("utf-8");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "Ra8#",
+            "Outcome: White wins!",
+            "R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1",
+            "[SetUp \"1\"]",
+            "[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]",
+            "",
+            "1. Ra8# 1-0",
+        ]
+    );
+}
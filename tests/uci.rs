@@ -0,0 +1,70 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use chess::{ai::Move, game::Game};
+
+/// the engine's response to `commands`
+fn run(commands: &[u8]) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .arg("--uci")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn engine");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(commands)
+        .expect("write commands");
+    let output = child.wait_with_output().expect("engine output");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).expect("utf-8")
+}
+
+#[test]
+fn bestmove_is_legal() {
+    let stdout = run(
+        b"uci\nisready\nucinewgame\nposition startpos moves e2e4 e7e5 g1f3\ngo depth 2\nquit\n",
+    );
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines.contains(&"uciok"), "{stdout}");
+    assert!(lines.contains(&"readyok"), "{stdout}");
+    let bestmove = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("bestmove "))
+        .expect("bestmove response");
+
+    let game = Game::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+        .expect("valid fen");
+    assert!(
+        Move::from_uci_str(bestmove, &game).is_ok(),
+        "illegal bestmove {bestmove}"
+    );
+}
+
+#[test]
+fn info_reports_principal_variation() {
+    let stdout = run(b"position startpos moves f2f3 e7e5 g2g4\ngo depth 3\nquit\n");
+    let info = stdout
+        .lines()
+        .find(|line| line.starts_with("info depth"))
+        .expect("info line");
+    assert!(
+        info.starts_with("info depth 3 score mate 1 nodes "),
+        "{info}"
+    );
+    assert!(info.ends_with(" pv d8h4"), "{info}");
+    assert!(stdout.contains("bestmove d8h4"), "{stdout}");
+
+    let stdout = run(b"position startpos\ngo depth 2\nquit\n");
+    let info = stdout
+        .lines()
+        .find(|line| line.starts_with("info depth"))
+        .expect("info line");
+    let pv: Vec<&str> = info.split(" pv ").nth(1).expect("pv").split(' ').collect();
+    assert_eq!(pv.len(), 2, "{info}");
+    assert!(info.contains(" score cp "), "{info}");
+}